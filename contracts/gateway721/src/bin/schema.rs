@@ -1,8 +1,8 @@
 use cosmwasm_schema::write_api;
 
 use cosmwasm_std::Empty;
-use gateway721::msg::InstantiateMsg;
 use cw721_base::Extension;
+use gateway721::msg::InstantiateMsg;
 
 pub type ExecuteMsg = gateway721::msg::ExecuteMsg<Extension, Empty>;
 pub type QueryMsg = gateway721::msg::QueryMsg<Empty>;
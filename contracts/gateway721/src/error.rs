@@ -0,0 +1,160 @@
+use cosmwasm_std::{StdError, Uint128};
+use cw_ownable::OwnershipError;
+use cw_utils::PaymentError;
+use thiserror::Error;
+
+use crate::state::Role;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error(transparent)]
+    Std(#[from] StdError),
+
+    #[error(transparent)]
+    Base(#[from] cw721_base::ContractError),
+
+    #[error(transparent)]
+    Ownership(#[from] OwnershipError),
+
+    #[error(transparent)]
+    Payment(#[from] PaymentError),
+
+    #[error("token_id already claimed")]
+    Claimed {},
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Task {task_id} not found")]
+    TaskNotFound { task_id: String },
+
+    #[error("Task {task_id} is already closed")]
+    TaskClosed { task_id: String },
+
+    #[error("{executor} already submitted an output for task {task_id}")]
+    AlreadySubmitted { executor: String, task_id: String },
+
+    #[error("Project {token_id} requires outputs to be committed and revealed")]
+    CommitRevealRequired { token_id: String },
+
+    #[error("Commit window for task {task_id} is over")]
+    CommitWindowClosed { task_id: String },
+
+    #[error("Task {task_id} cannot be revealed before block {height}")]
+    RevealTooEarly { task_id: String, height: u64 },
+
+    #[error("No commitment from {executor} for task {task_id}")]
+    CommitmentNotFound { executor: String, task_id: String },
+
+    #[error("Revealed output does not match the commitment")]
+    CommitmentMismatch {},
+
+    #[error("Executor staking is not enabled")]
+    StakingDisabled {},
+
+    #[error("Executors must bond at least {min_bond}")]
+    InsufficientBond { min_bond: Uint128 },
+
+    #[error("Task {task_id} is not due yet")]
+    TaskNotDue { task_id: String },
+
+    #[error("Task {task_id} is claimed by {executor}")]
+    TaskClaimed { task_id: String, executor: String },
+
+    #[error("Nothing to slash for task {task_id}")]
+    NotSlashable { task_id: String },
+
+    #[error("{executor} has no attestation key")]
+    ExecutorKeyNotFound { executor: String },
+
+    #[error("Attestation signature is invalid")]
+    InvalidAttestation {},
+
+    #[error("Schedule {id} not found")]
+    ScheduleNotFound { id: u64 },
+
+    #[error("Schedule {id} is not due")]
+    ScheduleNotDue { id: u64 },
+
+    #[error("Pipeline {id} not found")]
+    PipelineNotFound { id: u64 },
+
+    #[error("Child project {token_id} charges for requests")]
+    PricedChild { token_id: String },
+
+    #[error("Task {task_id} is completed by its children")]
+    CompositeTask { task_id: String },
+
+    #[error("Quorum must be at least one")]
+    InvalidQuorum {},
+
+    #[error("Project {token_id} does not accept {denom}")]
+    UnacceptedDenom { token_id: String, denom: String },
+
+    #[error("Revenue shares must sum to one")]
+    InvalidRevenueShare {},
+
+    #[error("Lineage share cannot exceed one")]
+    InvalidLineageShare {},
+
+    #[error("Project {token_id} is private and the sender holds no valid license")]
+    NoLicense { token_id: String },
+
+    #[error("Project {token_id} does not sell licenses")]
+    NoLicenseOffer { token_id: String },
+
+    #[error("The license to project {token_id} is still valid")]
+    LicenseActive { token_id: String },
+
+    #[error("Not enough credits to pay for project {token_id}")]
+    InsufficientCredits { token_id: String },
+
+    #[error("Project {token_id} has no plan {plan}")]
+    PlanNotFound { token_id: String, plan: u32 },
+
+    #[error("Subscription to project {token_id} is not on plan {plan} and cannot switch before it lapses")]
+    PlanMismatch { token_id: String, plan: u32 },
+
+    #[error("Subscription to project {token_id} has no requests left this period")]
+    QuotaExceeded { token_id: String },
+
+    #[error("Missing the {role} role")]
+    MissingRole { role: Role },
+
+    #[error("{operation} are paused")]
+    Paused { operation: String },
+
+    #[error("Project {token_id} is suspended")]
+    Suspended { token_id: String },
+
+    #[error("Project {token_id} allows at most {max} pending tasks")]
+    TooManyPending { token_id: String, max: u32 },
+
+    #[error("Project {token_id} allows at most {max} requests per {window} blocks")]
+    TooManyRequests {
+        token_id: String,
+        max: u32,
+        window: u64,
+    },
+
+    #[error("Token {token_id} is not listed")]
+    NotListed { token_id: String },
+
+    #[error("Royalty percentage cannot exceed {max}")]
+    RoyaltyTooHigh { max: u64 },
+
+    #[error("Nothing to withdraw")]
+    NothingToWithdraw {},
+
+    #[error("No rewards or completed tasks to distribute")]
+    NothingToDistribute {},
+
+    #[error("Message requires the Archway bindings")]
+    ArchwayOnly {},
+
+    #[error("Incorrect payment: expected {expected}, received {received}")]
+    IncorrectPayment {
+        expected: Uint128,
+        received: Uint128,
+    },
+}
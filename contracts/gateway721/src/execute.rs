@@ -1,17 +1,38 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::msg::{ExecuteMsg, IncompleteProjectsResponse, InstantiateMsg};
-use crate::state::{Extension, Gateway721Contract, OtherContractExecuteMsg, Task};
-use crate::traits::Gateway721Execute;
+use crate::error::ContractError;
+use crate::msg::{
+    attestation_hash, commitment_hash, Attestation, BatchItemResult, BatchResult, ExecuteMsg,
+    IncompleteProjectsResponse, InstantiateMsg, ReceiveMsg, RequestItem, ResponseItem,
+};
+use crate::receiver::{input_hash, Gateway721ReceiveMsg, RECEIVE_MSG_VERSION};
+use crate::state::{
+    add_balance, take_balance, Balance, Escrow, ExecutorKey, ExecutorStats, Extension,
+    Gateway721Contract, Interval, KeyScheme, License, LicenseOffer, Listing, Metadata,
+    PaymentSource, Pipeline, PipelineRun, PipelineStage, Plan, Price, RateLimit, RateLimits,
+    RevenueShare, Role, Schedule, StakingConfig, Submission, Subscription, Suspension, Task,
+    TaskClaim, TaskLink, Unbonding,
+};
+use crate::traits::{Gateway721, Gateway721Execute};
 
+use archway_bindings::types::rewards::RewardsRecordsResponse;
+use archway_bindings::{ArchwayMsg, ArchwayQuery};
 use cosmwasm_std::{
-    to_binary, Addr, CustomMsg, DepsMut, Env, MessageInfo, Response, StdResult, WasmMsg,
+    coins, from_json, to_json_binary, to_json_string, Addr, BankMsg, Binary, Coin, CosmosMsg,
+    CustomMsg, Decimal, Deps, DepsMut, Empty, Env, HexBinary, MessageInfo, Order, Response,
+    StdError, StdResult, Storage, Uint128, WasmMsg,
 };
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Denom};
+use cw721::Expiration;
 use cw721_base::state::TokenInfo;
-use cw721_base::{
-    ContractError, ExecuteMsg as Cw721ExecuteMsg, InstantiateMsg as Cw721InstantiateMsg,
-};
+use cw721_base::{ExecuteMsg as Cw721ExecuteMsg, InstantiateMsg as Cw721InstantiateMsg};
+use cw_storage_plus::Bound;
+use cw_utils::{must_pay, PaymentError};
+use sha2::{Digest, Sha256};
+
+/// Ancestors a fork passes its fees on to at most, the furthest one keeps the rest
+const MAX_LINEAGE_DEPTH: u32 = 10;
 
 impl<'a, T, C, E, Q> Gateway721Contract<'a, T, C, E, Q>
 where
@@ -50,44 +71,322 @@ where
     }
 }
 
+impl<'a, E, Q> Gateway721Contract<'a, Extension, ArchwayMsg, E, Q>
+where
+    E: CustomMsg,
+    Q: CustomMsg,
+{
+    pub fn execute(
+        &self,
+        deps: DepsMut<ArchwayQuery>,
+        env: Env,
+        info: MessageInfo,
+        msg: ExecuteMsg<Extension, E>,
+    ) -> Result<Response<ArchwayMsg>, ContractError> {
+        match msg {
+            ExecuteMsg::DistributeRewards {} => self.distribute_rewards(deps, env, info),
+            ExecuteMsg::SetFlatFee { amount } => self.set_flat_fee(deps, env, info, amount),
+            _ => self.execute_base(deps.into_empty(), env, info, msg),
+        }
+    }
+
+    fn set_flat_fee(
+        &self,
+        deps: DepsMut<ArchwayQuery>,
+        env: Env,
+        info: MessageInfo,
+        amount: Coin,
+    ) -> Result<Response<ArchwayMsg>, ContractError> {
+        self.assert_role(deps.storage, &info.sender, Role::Treasurer)?;
+
+        Ok(Response::new()
+            .add_message(ArchwayMsg::set_flat_fee(
+                env.contract.address,
+                amount.clone(),
+            ))
+            .add_attribute("action", "set_flat_fee")
+            .add_attribute("amount", amount.to_string()))
+    }
+
+    fn distribute_rewards(
+        &self,
+        deps: DepsMut<ArchwayQuery>,
+        env: Env,
+        info: MessageInfo,
+    ) -> Result<Response<ArchwayMsg>, ContractError> {
+        self.assert_role(deps.storage, &info.sender, Role::Treasurer)?;
+
+        let records: RewardsRecordsResponse = deps
+            .querier
+            .query(&ArchwayQuery::rewards_records(env.contract.address).into())?;
+        let total_points = self
+            .reward_points_total
+            .may_load(deps.storage)?
+            .unwrap_or_default();
+        if records.records.is_empty() || total_points == 0 {
+            return Err(ContractError::NothingToDistribute {});
+        }
+
+        let mut rewards: Vec<Coin> = vec![];
+        for coin in records.records.iter().flat_map(|record| &record.rewards) {
+            match rewards.iter_mut().find(|c| c.denom == coin.denom) {
+                Some(c) => c.amount += coin.amount,
+                None => rewards.push(coin.clone()),
+            }
+        }
+
+        let points = self
+            .reward_points
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for coin in &rewards {
+            let denom = Denom::Native(coin.denom.clone());
+            let mut distributed = Uint128::zero();
+            for (index, (address, count)) in points.iter().enumerate() {
+                // the last recipient also gets the rounding dust
+                let amount = if index + 1 == points.len() {
+                    coin.amount - distributed
+                } else {
+                    coin.amount.multiply_ratio(*count, total_points)
+                };
+                self.credit(deps.storage, address, &denom, amount)?;
+                distributed += amount;
+            }
+        }
+
+        self.reward_points.clear(deps.storage);
+        self.reward_points_total.save(deps.storage, &0)?;
+
+        let record_ids = records.records.iter().map(|record| record.id).collect();
+        Ok(Response::new()
+            .add_message(ArchwayMsg::withdraw_rewards_by_ids(record_ids))
+            .add_attribute("action", "distribute_rewards")
+            .add_attribute("total_points", total_points.to_string()))
+    }
+}
+
 impl<'a, C, E, Q> Gateway721Contract<'a, Extension, C, E, Q>
 where
     C: CustomMsg,
     E: CustomMsg,
     Q: CustomMsg,
 {
-    pub fn execute(
+    /// Handles every message that does not need the Archway bindings
+    pub fn execute_base(
         &self,
-        deps: DepsMut,
+        mut deps: DepsMut,
         env: Env,
         info: MessageInfo,
         msg: ExecuteMsg<Extension, E>,
     ) -> Result<Response<C>, ContractError> {
+        self.assert_not_paused(deps.storage, &msg)?;
         match msg {
             ExecuteMsg::Mint {
                 token_id: _,
                 owner,
                 token_uri,
                 extension,
-            } => self.mint_anyone(deps, info, owner, token_uri, extension),
-            ExecuteMsg::Request { token_id, input } => {
-                self.request(deps, env, info, token_id, input)
+            } => {
+                self.assert_children(deps.storage, &extension)?;
+                let extension = self.check_royalty(deps.as_ref(), &owner, extension)?;
+                self.mint_anyone(deps, info, owner, token_uri, extension)
             }
+            ExecuteMsg::Request {
+                token_id,
+                input,
+                not_before,
+            } => self.request(deps, env, info, token_id, input, not_before),
             ExecuteMsg::Response {
                 token_id,
                 task_id,
                 output,
-            } => self.response(deps, env, info, token_id, task_id, output),
+                attestation,
+            } => self.response(deps, env, info, token_id, task_id, output, attestation),
             ExecuteMsg::Update {
                 token_id,
                 title,
                 description,
             } => self.update(deps, env, info, token_id, title, description),
-            _ => self.cw721.execute(deps, env, info, msg.into()),
+            ExecuteMsg::Receive(msg) => self.receive(deps, env, info, msg),
+            ExecuteMsg::CancelRequest { token_id, task_id } => {
+                self.cancel_request(deps, env, info, token_id, task_id)
+            }
+            ExecuteMsg::SetPrices { token_id, prices } => {
+                self.set_prices(deps, env, info, token_id, prices)
+            }
+            ExecuteMsg::SetRevenueShare {
+                owner,
+                executor,
+                treasury,
+                treasury_address,
+            } => {
+                self.set_revenue_share(deps, env, info, owner, executor, treasury, treasury_address)
+            }
+            ExecuteMsg::DepositCredits {} => self.deposit_credits(deps, env, info),
+            ExecuteMsg::WithdrawCredits { amount } => {
+                self.withdraw_credits(deps, env, info, amount)
+            }
+            ExecuteMsg::SetPlans { token_id, plans } => {
+                self.set_plans(deps, env, info, token_id, plans)
+            }
+            ExecuteMsg::Subscribe { token_id, plan } => {
+                self.subscribe(deps, env, info, token_id, plan)
+            }
+            ExecuteMsg::Unsubscribe { token_id } => self.unsubscribe(deps, env, info, token_id),
+            ExecuteMsg::SetRateLimits {
+                token_id,
+                per_address,
+                per_project,
+                exempt,
+            } => self.set_rate_limits(deps, env, info, token_id, per_address, per_project, exempt),
+            ExecuteMsg::SetPrivate { token_id, private } => {
+                self.set_private(deps, env, info, token_id, private)
+            }
+            ExecuteMsg::IssueLicense {
+                token_id,
+                holder,
+                expires,
+                uses,
+            } => self.issue_license(deps, env, info, token_id, holder, expires, uses),
+            ExecuteMsg::RevokeLicense { token_id, holder } => {
+                self.revoke_license(deps, env, info, token_id, holder)
+            }
+            ExecuteMsg::SetLicenseOffer { token_id, offer } => {
+                self.set_license_offer(deps, env, info, token_id, offer)
+            }
+            ExecuteMsg::BuyLicense { token_id } => self.buy_license(deps, env, info, token_id),
+            ExecuteMsg::ListNft { token_id, price } => {
+                self.list_nft(deps, env, info, token_id, price)
+            }
+            ExecuteMsg::DelistNft { token_id } => self.delist_nft(deps, env, info, token_id),
+            ExecuteMsg::BuyNft { token_id } => self.buy_nft(deps, env, info, token_id),
+            ExecuteMsg::SetMarketFee { fee } => self.set_market_fee(deps, env, info, fee),
+            ExecuteMsg::SetMaxRoyaltyPercentage { percentage } => {
+                self.set_max_royalty_percentage(deps, env, info, percentage)
+            }
+            ExecuteMsg::SetLineageShare { share } => self.set_lineage_share(deps, env, info, share),
+            ExecuteMsg::Fork { token_id, code } => self.fork(deps, env, info, token_id, code),
+            ExecuteMsg::Withdraw { start_after, limit } => {
+                self.withdraw(deps, env, info, start_after, limit)
+            }
+            ExecuteMsg::AddExecutor { address } => self.add_executor(deps, env, info, address),
+            ExecuteMsg::RemoveExecutor { address } => {
+                self.remove_executor(deps, env, info, address)
+            }
+            ExecuteMsg::SetQuorum { token_id, quorum } => {
+                self.set_quorum(deps, env, info, token_id, quorum)
+            }
+            ExecuteMsg::SetCommitWindow { token_id, blocks } => {
+                self.set_commit_window(deps, env, info, token_id, blocks)
+            }
+            ExecuteMsg::CommitOutput {
+                token_id,
+                task_id,
+                hash,
+            } => self.commit_output(deps, env, info, token_id, task_id, hash),
+            ExecuteMsg::RevealOutput {
+                token_id,
+                task_id,
+                output,
+                salt,
+            } => self.reveal_output(deps, env, info, token_id, task_id, output, salt),
+            ExecuteMsg::SetStaking {
+                denom,
+                min_bond,
+                unbonding_period,
+                slash_ratio,
+                response_timeout,
+            } => self.set_staking(
+                deps,
+                env,
+                info,
+                denom,
+                min_bond,
+                unbonding_period,
+                slash_ratio,
+                response_timeout,
+            ),
+            ExecuteMsg::RegisterExecutor {} => self.register_executor(deps, env, info),
+            ExecuteMsg::Unbond { amount } => self.unbond(deps, env, info, amount),
+            ExecuteMsg::ClaimUnbonded {} => self.claim_unbonded(deps, env, info),
+            ExecuteMsg::ClaimTask { token_id, task_id } => {
+                self.claim_task(deps, env, info, token_id, task_id)
+            }
+            ExecuteMsg::SlashExpiredClaim { token_id, task_id } => {
+                self.slash_expired_claim(deps, env, info, token_id, task_id)
+            }
+            ExecuteMsg::SlashUnrevealed {
+                token_id,
+                task_id,
+                executor,
+            } => self.slash_unrevealed(deps, env, info, token_id, task_id, executor),
+            ExecuteMsg::SetAllowlist {
+                token_id,
+                executors,
+            } => self.set_allowlist(deps, env, info, token_id, executors),
+            ExecuteMsg::ScheduleRequest {
+                token_id,
+                input,
+                interval,
+                max_runs,
+            } => self.schedule_request(deps, env, info, token_id, input, interval, max_runs),
+            ExecuteMsg::TriggerSchedule { id } => self.trigger_schedule(deps, env, info, id),
+            ExecuteMsg::PauseSchedule { id } => self.pause_schedule(deps, env, info, id, true),
+            ExecuteMsg::ResumeSchedule { id } => self.pause_schedule(deps, env, info, id, false),
+            ExecuteMsg::CancelSchedule { id } => self.cancel_schedule(deps, env, info, id),
+            ExecuteMsg::BatchRequest { items } => self.batch_request(deps, env, info, items),
+            ExecuteMsg::BatchResponse { items } => self.batch_response(deps, env, info, items),
+            ExecuteMsg::CreatePipeline {
+                stages,
+                destination,
+            } => self.create_pipeline(deps, env, info, stages, destination),
+            ExecuteMsg::RunPipeline { id, input } => self.run_pipeline(deps, env, info, id, input),
+            ExecuteMsg::SetExecutorKey { scheme, pubkey } => {
+                self.set_executor_key(deps, env, info, scheme, pubkey)
+            }
+            ExecuteMsg::SetPauses {
+                minting,
+                requests,
+                responses,
+                transfers,
+            } => self.set_pauses(deps, env, info, minting, requests, responses, transfers),
+            ExecuteMsg::Suspend { token_id } => self.suspend(deps, env, info, token_id, true),
+            ExecuteMsg::Unsuspend { token_id } => self.suspend(deps, env, info, token_id, false),
+            ExecuteMsg::GrantRole { address, role } => {
+                self.set_role(deps, env, info, address, role, true)
+            }
+            ExecuteMsg::RevokeRole { address, role } => {
+                self.set_role(deps, env, info, address, role, false)
+            }
+            ExecuteMsg::DistributeRewards {} | ExecuteMsg::SetFlatFee { .. } => {
+                Err(ContractError::ArchwayOnly {})
+            }
+            _ => {
+                let moved = match &msg {
+                    ExecuteMsg::TransferNft { token_id, .. }
+                    | ExecuteMsg::SendNft { token_id, .. }
+                    | ExecuteMsg::Burn { token_id } => Some(token_id.clone()),
+                    _ => None,
+                };
+                let res = self.cw721.execute(deps.branch(), env, info, msg.into())?;
+                // a listing never outlives its token changing hands
+                if let Some(token_id) = moved {
+                    self.listings.remove(deps.storage, &token_id)?;
+                }
+                Ok(res)
+            }
         }
     }
 }
 
+impl<'a, C, E, Q> Gateway721<Extension, C> for Gateway721Contract<'a, Extension, C, E, Q>
+where
+    C: CustomMsg,
+    E: CustomMsg,
+    Q: CustomMsg,
+{
+}
+
 impl<'a, C, E, Q> Gateway721Execute<Extension, C> for Gateway721Contract<'a, Extension, C, E, Q>
 where
     C: CustomMsg,
@@ -103,100 +402,84 @@ where
         info: MessageInfo,
         token_id: String,
         input: String,
+        not_before: Option<Expiration>,
     ) -> Result<Response<C>, Self::Err> {
-        let mut token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
-        let new_tid;
-
-        if let Some(ref mut metadata) = token.extension {
-            // Initialize the tasks vector if it does not exist
-            if metadata.tasks.is_none() {
-                metadata.tasks = Some(Vec::new());
-            }
+        let payment = if info.funds.is_empty() {
+            self.prepaid_payment(deps.storage, &env, &info.sender, &token_id)?
+                .0
+        } else {
+            self.native_payment(deps.storage, &token_id, &info.funds)?
+        };
+        self.create_task(
+            deps,
+            &env,
+            info.sender,
+            token_id,
+            input,
+            not_before,
+            payment,
+        )
+    }
 
-            // Generate a new id for the task
-            new_tid = (metadata.tasks.as_ref().unwrap().len()).to_string();
+    fn receive(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Cw20ReceiveMsg,
+    ) -> Result<Response<C>, Self::Err> {
+        let requester = deps.api.addr_validate(&msg.sender)?;
 
-            // Push the new task to the tasks vector
-            if let Some(ref mut tasks) = metadata.tasks {
-                tasks.push(Task {
-                    tid: new_tid.clone(),
+        match from_json(&msg.msg)? {
+            ReceiveMsg::Request {
+                token_id,
+                input,
+                not_before,
+            } => {
+                let price = self.check_payment(
+                    deps.storage,
+                    &token_id,
+                    Denom::Cw20(info.sender),
+                    msg.amount,
+                )?;
+                self.create_task(
+                    deps,
+                    &env,
+                    requester,
+                    token_id,
                     input,
-                    output: None,
-                });
-            } else {
-                return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
-                    "tasks are not valid.",
-                )));
+                    not_before,
+                    Some(price),
+                )
             }
-        } else {
-            return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
-                "token extension is not valid.",
-            )));
         }
-
-        // Save the updated token back to storage
-        self.cw721.tokens.save(deps.storage, &token_id, &token)?;
-
-        Ok(Response::new()
-            .add_attribute("action", "request")
-            .add_attribute("requester", info.sender)
-            .add_attribute("token_id", token_id)
-            .add_attribute("task_id", new_tid))
     }
 
     fn response(
         &self,
-        deps: cosmwasm_std::DepsMut,
+        mut deps: cosmwasm_std::DepsMut,
         env: Env,
         info: cosmwasm_std::MessageInfo,
         token_id: String,
         task_id: String,
         output: String,
+        attestation: Option<Attestation>,
     ) -> Result<Response<C>, Self::Err> {
-        cw_ownable::assert_owner(deps.storage, &info.sender)?;
-
-        let mut token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
-
-        if let Some(ref mut metadata) = token.extension {
-            if let Some(ref mut tasks) = metadata.tasks {
-                // Find the task with the specified id and set the output
-                if let Some(task) = tasks.iter_mut().find(|task| task.tid == task_id) {
-                    task.output = Some(output.clone());
-                } else {
-                    return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
-                        "task not found.",
-                    )));
-                }
-            } else {
-                return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
-                    "tasks are not valid.",
-                )));
-            }
-        } else {
-            return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
-                "token extension is not valid.",
-            )));
-        }
-
-        // Save the updated token back to storage
-        self.cw721.tokens.save(deps.storage, &token_id, &token)?;
-
-        // send `output` to destination address
-        if let Some(ref mut metadata) = token.extension {
-            if let Some(ref mut dest) = metadata.destination {
-                let msg = WasmMsg::Execute {
-                    contract_addr: dest.to_string(),
-                    msg: to_binary(&OtherContractExecuteMsg::ReceiveOutput {
-                        output: output.clone(),
-                    })?,
-                    funds: vec![],
-                };
-            }
-        }
+        let (executor, outcome) = self.respond(
+            &mut deps,
+            &env,
+            info.sender,
+            &token_id,
+            &task_id,
+            output,
+            attestation,
+        )?;
+        let res = submission_response(outcome);
 
         // Create response
-        Ok(Response::new()
+        Ok(res
             .add_attribute("action", "response")
+            .add_attribute("executor", executor)
             .add_attribute("token_id", token_id)
             .add_attribute("task_id", task_id.to_string()))
     }
@@ -204,13 +487,13 @@ where
     fn update(
         &self,
         deps: cosmwasm_std::DepsMut,
-        env: Env,
+        _env: Env,
         info: cosmwasm_std::MessageInfo,
         token_id: String,
         title: String,
         description: String,
     ) -> Result<Response<C>, Self::Err> {
-        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+        self.assert_role(deps.storage, &info.sender, Role::Describer)?;
 
         let mut token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
         if let Some(mut extension) = token.extension {
@@ -253,6 +536,3252 @@ where
             .add_attribute("action", "response")
             .add_attribute("token_id", token_id))
     }
+
+    fn cancel_request(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+        task_id: String,
+    ) -> Result<Response<C>, Self::Err> {
+        let mut token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
+
+        let task = find_task_mut(&mut token, &task_id)?;
+        if task.requester.as_ref() != Some(&info.sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+        if task.output.is_some() || task.cancelled {
+            return Err(ContractError::TaskClosed { task_id });
+        }
+        task.cancelled = true;
+
+        self.cw721.tokens.save(deps.storage, &token_id, &token)?;
+        self.claims.remove(deps.storage, (&token_id, &task_id));
+        // children of a cancelled composite stay open but their outputs are no longer collected
+        self.fan_outs.remove(deps.storage, (&token_id, &task_id));
+        self.fan_out_executors
+            .remove(deps.storage, (&token_id, &task_id));
+
+        // refund the payment to the requester
+        let mut res = Response::new();
+        if let Some(escrow) = self.escrows.may_load(deps.storage, (&token_id, &task_id))? {
+            self.escrows.remove(deps.storage, (&token_id, &task_id));
+            res = res.add_message(transfer_msg(&escrow.payer, &escrow.denom, escrow.amount)?);
+        }
+        // along with the stages a pipeline run will no longer request
+        if let Some(TaskLink::Pipeline { run_id }) = self
+            .task_links
+            .may_load(deps.storage, (&token_id, &task_id))?
+        {
+            self.task_links.remove(deps.storage, (&token_id, &task_id));
+            let run = self.pipeline_runs.load(deps.storage, run_id)?;
+            self.pipeline_runs.remove(deps.storage, run_id);
+            let msgs = self.refund_stages(deps.storage, &run, run.stage as usize + 1)?;
+            res = res.add_messages(msgs);
+        }
+
+        Ok(res
+            .add_attribute("action", "cancel_request")
+            .add_attribute("token_id", token_id)
+            .add_attribute("task_id", task_id))
+    }
+
+    fn set_prices(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+        prices: Vec<Price>,
+    ) -> Result<Response<C>, Self::Err> {
+        let token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
+        if token.owner != info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        let mut checked: Vec<Price> = Vec::with_capacity(prices.len());
+        for price in prices {
+            let denom = match price.denom {
+                Denom::Native(denom) => Denom::Native(denom),
+                Denom::Cw20(addr) => Denom::Cw20(deps.api.addr_validate(addr.as_str())?),
+            };
+            if price.amount.is_zero() || checked.iter().any(|p| p.denom == denom) {
+                return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+                    "prices must be non-zero and unique per denom.",
+                )));
+            }
+            checked.push(Price {
+                denom,
+                amount: price.amount,
+            });
+        }
+
+        if checked.is_empty() {
+            self.prices.remove(deps.storage, &token_id);
+        } else {
+            self.prices.save(deps.storage, &token_id, &checked)?;
+        }
+
+        Ok(Response::new()
+            .add_attribute("action", "set_prices")
+            .add_attribute("token_id", token_id))
+    }
+
+    fn set_revenue_share(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        owner: Decimal,
+        executor: Decimal,
+        treasury: Decimal,
+        treasury_address: String,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_role(deps.storage, &info.sender, Role::Treasurer)?;
+
+        if owner + executor + treasury != Decimal::one() {
+            return Err(ContractError::InvalidRevenueShare {});
+        }
+        let share = RevenueShare {
+            owner,
+            executor,
+            treasury,
+            treasury_address: deps.api.addr_validate(&treasury_address)?,
+        };
+        self.revenue_share.save(deps.storage, &share)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_revenue_share")
+            .add_attribute("owner", owner.to_string())
+            .add_attribute("executor", executor.to_string())
+            .add_attribute("treasury", treasury.to_string()))
+    }
+
+    fn add_executor(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        address: String,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_role(deps.storage, &info.sender, Role::ExecutorManager)?;
+
+        let executor = deps.api.addr_validate(&address)?;
+        self.set_registered(deps.storage, &executor, true)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "add_executor")
+            .add_attribute("executor", executor))
+    }
+
+    fn remove_executor(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        address: String,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_role(deps.storage, &info.sender, Role::ExecutorManager)?;
+
+        let executor = deps.api.addr_validate(&address)?;
+        self.set_registered(deps.storage, &executor, false)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "remove_executor")
+            .add_attribute("executor", executor))
+    }
+
+    fn set_quorum(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+        quorum: u32,
+    ) -> Result<Response<C>, Self::Err> {
+        let token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
+        if token.owner != info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+        if quorum == 0 {
+            return Err(ContractError::InvalidQuorum {});
+        }
+
+        if quorum == 1 {
+            self.quorums.remove(deps.storage, &token_id);
+        } else {
+            self.quorums.save(deps.storage, &token_id, &quorum)?;
+        }
+
+        Ok(Response::new()
+            .add_attribute("action", "set_quorum")
+            .add_attribute("token_id", token_id)
+            .add_attribute("quorum", quorum.to_string()))
+    }
+
+    fn set_commit_window(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+        blocks: u64,
+    ) -> Result<Response<C>, Self::Err> {
+        let token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
+        if token.owner != info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        if blocks == 0 {
+            self.commit_windows.remove(deps.storage, &token_id);
+        } else {
+            self.commit_windows.save(deps.storage, &token_id, &blocks)?;
+        }
+
+        Ok(Response::new()
+            .add_attribute("action", "set_commit_window")
+            .add_attribute("token_id", token_id)
+            .add_attribute("blocks", blocks.to_string()))
+    }
+
+    fn commit_output(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        task_id: String,
+        hash: HexBinary,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_project_executor(deps.storage, &token_id, &info.sender)?;
+
+        let mut token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
+        let task = find_task_mut(&mut token, &task_id)?;
+        if task.cancelled || task.output.is_some() {
+            return Err(ContractError::TaskClosed { task_id });
+        }
+        let deadline = self
+            .commit_deadline(deps.storage, &token_id, task)?
+            .ok_or_else(|| ContractError::Std(StdError::generic_err("no commit window.")))?;
+        if env.block.height > deadline {
+            return Err(ContractError::CommitWindowClosed { task_id });
+        }
+
+        let key = (token_id.as_str(), task_id.as_str(), &info.sender);
+        if self.commitments.has(deps.storage, key) {
+            return Err(ContractError::AlreadySubmitted {
+                executor: info.sender.to_string(),
+                task_id,
+            });
+        }
+        self.commitments.save(deps.storage, key, &hash)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "commit_output")
+            .add_attribute("executor", info.sender)
+            .add_attribute("token_id", token_id)
+            .add_attribute("task_id", task_id))
+    }
+
+    fn reveal_output(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        task_id: String,
+        output: String,
+        salt: String,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_project_executor(deps.storage, &token_id, &info.sender)?;
+
+        let key = (token_id.as_str(), task_id.as_str(), &info.sender);
+        let hash = self
+            .commitments
+            .may_load(deps.storage, key)?
+            .ok_or_else(|| ContractError::CommitmentNotFound {
+                executor: info.sender.to_string(),
+                task_id: task_id.clone(),
+            })?;
+
+        let mut token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
+        let task = find_task_mut(&mut token, &task_id)?;
+        let deadline = self
+            .commit_deadline(deps.storage, &token_id, task)?
+            .unwrap_or_default();
+        if env.block.height <= deadline {
+            return Err(ContractError::RevealTooEarly {
+                task_id,
+                height: deadline + 1,
+            });
+        }
+        if commitment_hash(info.sender.as_str(), &salt, &output) != hash {
+            return Err(ContractError::CommitmentMismatch {});
+        }
+        self.commitments.remove(deps.storage, key);
+
+        let outcome = self.submit_output(
+            deps.storage,
+            &env,
+            &token_id,
+            &task_id,
+            &info.sender,
+            output,
+        )?;
+        Ok(submission_response(outcome)
+            .add_attribute("action", "reveal_output")
+            .add_attribute("executor", info.sender)
+            .add_attribute("token_id", token_id)
+            .add_attribute("task_id", task_id))
+    }
+
+    fn set_staking(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        denom: String,
+        min_bond: Uint128,
+        unbonding_period: u64,
+        slash_ratio: Decimal,
+        response_timeout: u64,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_role(deps.storage, &info.sender, Role::ExecutorManager)?;
+
+        if let Some(config) = self.staking.may_load(deps.storage)? {
+            if config.denom != denom {
+                return Err(ContractError::Std(StdError::generic_err(
+                    "staking denom cannot change.",
+                )));
+            }
+        }
+        if slash_ratio > Decimal::one() {
+            return Err(ContractError::Std(StdError::generic_err(
+                "slash ratio cannot exceed one.",
+            )));
+        }
+        let config = StakingConfig {
+            denom,
+            min_bond,
+            unbonding_period,
+            slash_ratio,
+            response_timeout,
+        };
+        self.staking.save(deps.storage, &config)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_staking")
+            .add_attribute("denom", config.denom)
+            .add_attribute("min_bond", min_bond))
+    }
+
+    fn register_executor(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+    ) -> Result<Response<C>, Self::Err> {
+        let config = self
+            .staking
+            .may_load(deps.storage)?
+            .ok_or(ContractError::StakingDisabled {})?;
+        let amount = must_pay(&info, &config.denom)?;
+
+        let mut stake = self
+            .stakes
+            .may_load(deps.storage, &info.sender)?
+            .unwrap_or_default();
+        stake.bonded += amount;
+        if stake.bonded < config.min_bond {
+            return Err(ContractError::InsufficientBond {
+                min_bond: config.min_bond,
+            });
+        }
+        self.stakes.save(deps.storage, &info.sender, &stake)?;
+        self.set_registered(deps.storage, &info.sender, true)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "register_executor")
+            .add_attribute("executor", info.sender)
+            .add_attribute("bonded", stake.bonded))
+    }
+
+    fn unbond(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        amount: Uint128,
+    ) -> Result<Response<C>, Self::Err> {
+        let config = self
+            .staking
+            .may_load(deps.storage)?
+            .ok_or(ContractError::StakingDisabled {})?;
+        let mut stake = self
+            .stakes
+            .may_load(deps.storage, &info.sender)?
+            .unwrap_or_default();
+        if amount.is_zero() || amount > stake.bonded {
+            return Err(ContractError::Std(StdError::generic_err(
+                "amount must be non-zero and bonded.",
+            )));
+        }
+
+        stake.bonded -= amount;
+        let release_at = env.block.time.plus_seconds(config.unbonding_period);
+        stake.unbonding.push(Unbonding { amount, release_at });
+        self.stakes.save(deps.storage, &info.sender, &stake)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "unbond")
+            .add_attribute("executor", info.sender)
+            .add_attribute("amount", amount)
+            .add_attribute("release_at", release_at.to_string()))
+    }
+
+    fn claim_unbonded(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+    ) -> Result<Response<C>, Self::Err> {
+        let config = self
+            .staking
+            .may_load(deps.storage)?
+            .ok_or(ContractError::StakingDisabled {})?;
+        let mut stake = self
+            .stakes
+            .may_load(deps.storage, &info.sender)?
+            .unwrap_or_default();
+
+        let (released, unbonding): (Vec<_>, Vec<_>) = stake
+            .unbonding
+            .into_iter()
+            .partition(|unbonding| unbonding.release_at <= env.block.time);
+        let amount: Uint128 = released.iter().map(|unbonding| unbonding.amount).sum();
+        if amount.is_zero() {
+            return Err(ContractError::NothingToWithdraw {});
+        }
+        stake.unbonding = unbonding;
+        self.stakes.save(deps.storage, &info.sender, &stake)?;
+
+        Ok(Response::new()
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: coins(amount.u128(), config.denom),
+            })
+            .add_attribute("action", "claim_unbonded")
+            .add_attribute("executor", info.sender)
+            .add_attribute("amount", amount))
+    }
+
+    fn claim_task(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        task_id: String,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_project_executor(deps.storage, &token_id, &info.sender)?;
+        let config = self
+            .staking
+            .may_load(deps.storage)?
+            .ok_or(ContractError::StakingDisabled {})?;
+        if self.quorums.has(deps.storage, &token_id)
+            || self.commit_windows.has(deps.storage, &token_id)
+        {
+            return Err(ContractError::Std(StdError::generic_err(
+                "only single-executor tasks can be claimed.",
+            )));
+        }
+
+        let mut token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
+        let task = find_task_mut(&mut token, &task_id)?;
+        if task.cancelled || task.output.is_some() {
+            return Err(ContractError::TaskClosed { task_id });
+        }
+        assert_due(task, &env)?;
+        self.assert_unclaimed(deps.storage, &env, &token_id, &task_id, &info.sender)?;
+
+        let claim = TaskClaim {
+            executor: info.sender.clone(),
+            claimed_at: env.block.height,
+            expires_at: env.block.height + config.response_timeout,
+        };
+        self.claims
+            .save(deps.storage, (&token_id, &task_id), &claim)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "claim_task")
+            .add_attribute("executor", info.sender)
+            .add_attribute("token_id", token_id)
+            .add_attribute("task_id", task_id)
+            .add_attribute("expires_at", claim.expires_at.to_string()))
+    }
+
+    fn slash_expired_claim(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        _info: MessageInfo,
+        token_id: String,
+        task_id: String,
+    ) -> Result<Response<C>, Self::Err> {
+        let claim = self
+            .claims
+            .may_load(deps.storage, (&token_id, &task_id))?
+            .ok_or_else(|| ContractError::NotSlashable {
+                task_id: task_id.clone(),
+            })?;
+        if !self.claim_expired(deps.storage, &env, &token_id, &claim)? {
+            return Err(ContractError::NotSlashable { task_id });
+        }
+        self.claims.remove(deps.storage, (&token_id, &task_id));
+        let amount = self.slash(deps.storage, &claim.executor)?;
+        self.record_stats(deps.storage, &claim.executor, |stats| {
+            stats.expired_claims += 1
+        })?;
+
+        Ok(Response::new()
+            .add_attribute("action", "slash_expired_claim")
+            .add_attribute("executor", claim.executor)
+            .add_attribute("token_id", token_id)
+            .add_attribute("task_id", task_id)
+            .add_attribute("amount", amount))
+    }
+
+    fn slash_unrevealed(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        _info: MessageInfo,
+        token_id: String,
+        task_id: String,
+        executor: String,
+    ) -> Result<Response<C>, Self::Err> {
+        let config = self
+            .staking
+            .may_load(deps.storage)?
+            .ok_or(ContractError::StakingDisabled {})?;
+        let executor = deps.api.addr_validate(&executor)?;
+        let key = (token_id.as_str(), task_id.as_str(), &executor);
+        if !self.commitments.has(deps.storage, key) {
+            return Err(ContractError::CommitmentNotFound {
+                executor: executor.to_string(),
+                task_id,
+            });
+        }
+
+        let mut token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
+        let task = find_task_mut(&mut token, &task_id)?;
+        // reveals open once the task is due, a due time gives no height to count from
+        let due_at = match task.not_before {
+            None => 0,
+            Some(Expiration::AtHeight(height)) => height,
+            Some(_) => return Err(ContractError::NotSlashable { task_id }),
+        };
+        let reveals_open = self
+            .commit_deadline(deps.storage, &token_id, task)?
+            .unwrap_or_default()
+            .max(due_at);
+        let reveal_deadline = reveals_open
+            + config.response_timeout
+            + self.halted_blocks(deps.storage, &token_id, reveals_open, env.block.height)?;
+        if task.cancelled || task.output.is_some() || env.block.height <= reveal_deadline {
+            return Err(ContractError::NotSlashable { task_id });
+        }
+        self.commitments.remove(deps.storage, key);
+        let amount = self.slash(deps.storage, &executor)?;
+        self.record_stats(deps.storage, &executor, |stats| stats.failed += 1)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "slash_unrevealed")
+            .add_attribute("executor", executor)
+            .add_attribute("token_id", token_id)
+            .add_attribute("task_id", task_id)
+            .add_attribute("amount", amount))
+    }
+
+    fn set_allowlist(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+        executors: Vec<String>,
+    ) -> Result<Response<C>, Self::Err> {
+        let token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
+        if token.owner != info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        let executors = executors
+            .iter()
+            .map(|executor| deps.api.addr_validate(executor))
+            .collect::<StdResult<Vec<_>>>()?;
+        if executors.is_empty() {
+            self.allowlists.remove(deps.storage, &token_id);
+        } else {
+            self.allowlists.save(deps.storage, &token_id, &executors)?;
+        }
+
+        Ok(Response::new()
+            .add_attribute("action", "set_allowlist")
+            .add_attribute("token_id", token_id)
+            .add_attribute("executors", executors.len().to_string()))
+    }
+
+    fn schedule_request(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        input: String,
+        interval: Interval,
+        max_runs: u32,
+    ) -> Result<Response<C>, Self::Err> {
+        // make sure the project exists before taking the payment
+        self.cw721.tokens.load(deps.storage, &token_id)?;
+        if max_runs == 0 {
+            return Err(ContractError::Std(StdError::generic_err(
+                "max_runs must be at least one.",
+            )));
+        }
+        let price = self.schedule_payment(deps.storage, &token_id, &info.funds, max_runs)?;
+
+        let id = self
+            .schedule_count
+            .may_load(deps.storage)?
+            .unwrap_or_default();
+        self.schedule_count.save(deps.storage, &(id + 1))?;
+        let schedule = Schedule {
+            owner: info.sender,
+            token_id,
+            input,
+            interval,
+            max_runs,
+            runs: 0,
+            // the first run is due right away
+            next_run: Expiration::AtHeight(env.block.height),
+            paused: false,
+            price,
+        };
+        self.schedules.save(deps.storage, id, &schedule)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "schedule_request")
+            .add_attribute("owner", schedule.owner)
+            .add_attribute("token_id", schedule.token_id)
+            .add_attribute("schedule_id", id.to_string()))
+    }
+
+    fn trigger_schedule(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        _info: MessageInfo,
+        id: u64,
+    ) -> Result<Response<C>, Self::Err> {
+        let mut schedule = self
+            .schedules
+            .may_load(deps.storage, id)?
+            .ok_or(ContractError::ScheduleNotFound { id })?;
+        if schedule.paused || !schedule.next_run.is_expired(&env.block) {
+            return Err(ContractError::ScheduleNotDue { id });
+        }
+
+        // runs scheduled without funds are paid like requests without funds
+        let payment = match schedule.price.clone() {
+            Some(price) => Some(price),
+            None => {
+                self.prepaid_payment(deps.storage, &env, &schedule.owner, &schedule.token_id)?
+                    .0
+            }
+        };
+        schedule.runs += 1;
+        if schedule.runs < schedule.max_runs {
+            schedule.next_run = schedule.interval.after(&env.block);
+            self.schedules.save(deps.storage, id, &schedule)?;
+        } else {
+            self.schedules.remove(deps.storage, id)?;
+        }
+
+        let res = self.create_task(
+            deps,
+            &env,
+            schedule.owner,
+            schedule.token_id,
+            schedule.input,
+            None,
+            payment,
+        )?;
+        Ok(res
+            .add_attribute("schedule_id", id.to_string())
+            .add_attribute("run", schedule.runs.to_string()))
+    }
+
+    fn pause_schedule(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        id: u64,
+        paused: bool,
+    ) -> Result<Response<C>, Self::Err> {
+        let mut schedule = self
+            .schedules
+            .may_load(deps.storage, id)?
+            .ok_or(ContractError::ScheduleNotFound { id })?;
+        if schedule.owner != info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+        schedule.paused = paused;
+        self.schedules.save(deps.storage, id, &schedule)?;
+
+        let action = if paused {
+            "pause_schedule"
+        } else {
+            "resume_schedule"
+        };
+        Ok(Response::new()
+            .add_attribute("action", action)
+            .add_attribute("schedule_id", id.to_string()))
+    }
+
+    fn cancel_schedule(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        id: u64,
+    ) -> Result<Response<C>, Self::Err> {
+        let schedule = self
+            .schedules
+            .may_load(deps.storage, id)?
+            .ok_or(ContractError::ScheduleNotFound { id })?;
+        if schedule.owner != info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+        self.schedules.remove(deps.storage, id)?;
+
+        // refund the runs that were never triggered
+        let mut res = Response::new();
+        if let Some(price) = schedule.price {
+            let remaining = price.amount * Uint128::from(schedule.max_runs - schedule.runs);
+            res = res.add_message(transfer_msg(&schedule.owner, &price.denom, remaining)?);
+        }
+
+        Ok(res
+            .add_attribute("action", "cancel_schedule")
+            .add_attribute("schedule_id", id.to_string()))
+    }
+
+    fn batch_request(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        items: Vec<RequestItem>,
+    ) -> Result<Response<C>, Self::Err> {
+        if items.is_empty() {
+            return Err(ContractError::Std(StdError::generic_err("empty batch.")));
+        }
+        let token_ids: Vec<&str> = items.iter().map(|item| item.token_id.as_str()).collect();
+        let payments =
+            self.batch_payments(deps.storage, &env, &info.sender, &token_ids, &info.funds)?;
+
+        let mut results = Vec::with_capacity(items.len());
+        for (item, (payment, _)) in items.into_iter().zip(payments) {
+            self.assert_rate_limits(deps.storage, &env, &item.token_id, &info.sender)?;
+            let task_id = self.push_task(
+                deps.storage,
+                &env,
+                info.sender.clone(),
+                &item.token_id,
+                item.input,
+                item.not_before,
+                payment,
+            )?;
+            results.push(BatchItemResult {
+                token_id: item.token_id,
+                task_id,
+                status: "requested".to_string(),
+            });
+        }
+
+        Ok(Response::new()
+            .set_data(to_json_binary(&BatchResult { items: results })?)
+            .add_attribute("action", "batch_request")
+            .add_attribute("requester", info.sender))
+    }
+
+    fn batch_response(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        items: Vec<ResponseItem>,
+    ) -> Result<Response<C>, Self::Err> {
+        if items.is_empty() {
+            return Err(ContractError::Std(StdError::generic_err("empty batch.")));
+        }
+
+        let mut msgs = vec![];
+        let mut results = Vec::with_capacity(items.len());
+        for item in items {
+            let (_, outcome) = self.respond(
+                &mut deps,
+                &env,
+                info.sender.clone(),
+                &item.token_id,
+                &item.task_id,
+                item.output,
+                item.attestation,
+            )?;
+            let status = match outcome {
+                Some(finalized) => {
+                    msgs.extend(finalized);
+                    "finalized"
+                }
+                None => "pending_quorum",
+            };
+            results.push(BatchItemResult {
+                token_id: item.token_id,
+                task_id: item.task_id,
+                status: status.to_string(),
+            });
+        }
+
+        Ok(Response::new()
+            .add_messages(msgs)
+            .set_data(to_json_binary(&BatchResult { items: results })?)
+            .add_attribute("action", "batch_response")
+            .add_attribute("sender", info.sender))
+    }
+
+    fn create_pipeline(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        stages: Vec<PipelineStage>,
+        destination: Option<String>,
+    ) -> Result<Response<C>, Self::Err> {
+        if stages.is_empty() {
+            return Err(ContractError::Std(StdError::generic_err(
+                "pipelines need at least one stage.",
+            )));
+        }
+        for stage in &stages {
+            self.cw721.tokens.load(deps.storage, &stage.token_id)?;
+        }
+        let destination = destination
+            .map(|dest| deps.api.addr_validate(&dest))
+            .transpose()?;
+
+        let id = self
+            .pipeline_count
+            .may_load(deps.storage)?
+            .unwrap_or_default();
+        self.pipeline_count.save(deps.storage, &(id + 1))?;
+        let pipeline = Pipeline {
+            owner: info.sender,
+            stages,
+            destination,
+        };
+        self.pipelines.save(deps.storage, id, &pipeline)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "create_pipeline")
+            .add_attribute("owner", pipeline.owner)
+            .add_attribute("pipeline_id", id.to_string()))
+    }
+
+    fn run_pipeline(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        id: u64,
+        input: String,
+    ) -> Result<Response<C>, Self::Err> {
+        let pipeline = self
+            .pipelines
+            .may_load(deps.storage, id)?
+            .ok_or(ContractError::PipelineNotFound { id })?;
+        let token_ids: Vec<&str> = pipeline
+            .stages
+            .iter()
+            .map(|stage| stage.token_id.as_str())
+            .collect();
+        // every stage is paid upfront and held until its task is created
+        let (payments, sources) = self
+            .batch_payments(deps.storage, &env, &info.sender, &token_ids, &info.funds)?
+            .into_iter()
+            .unzip();
+
+        let run_id = self
+            .pipeline_run_count
+            .may_load(deps.storage)?
+            .unwrap_or_default();
+        self.pipeline_run_count.save(deps.storage, &(run_id + 1))?;
+        let run = PipelineRun {
+            pipeline_id: id,
+            requester: info.sender,
+            stage: 0,
+            payments,
+            sources,
+            failure: None,
+        };
+        let stage = &pipeline.stages[0];
+        self.assert_rate_limits(deps.storage, &env, &stage.token_id, &run.requester)?;
+        let task_id = self.request_stage(
+            deps.storage,
+            &env,
+            run_id,
+            &run,
+            stage,
+            input,
+            run.payments[0].clone(),
+        )?;
+
+        Ok(Response::new()
+            .add_attribute("action", "run_pipeline")
+            .add_attribute("requester", run.requester)
+            .add_attribute("pipeline_id", id.to_string())
+            .add_attribute("run_id", run_id.to_string())
+            .add_attribute("token_id", stage.token_id.clone())
+            .add_attribute("task_id", task_id))
+    }
+
+    fn set_executor_key(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        scheme: KeyScheme,
+        pubkey: Binary,
+    ) -> Result<Response<C>, Self::Err> {
+        let key = ExecutorKey { scheme, pubkey };
+        self.executor_keys.save(deps.storage, &info.sender, &key)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_executor_key")
+            .add_attribute("executor", info.sender)
+            .add_attribute("pubkey", key.pubkey.to_base64()))
+    }
+
+    fn deposit_credits(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+    ) -> Result<Response<C>, Self::Err> {
+        if info.funds.is_empty() {
+            return Err(PaymentError::NoFunds {}.into());
+        }
+
+        let mut credits = self
+            .credits
+            .may_load(deps.storage, &info.sender)?
+            .unwrap_or_default();
+        for coin in &info.funds {
+            add_balance(
+                &mut credits,
+                &Denom::Native(coin.denom.clone()),
+                coin.amount,
+            );
+        }
+        self.credits.save(deps.storage, &info.sender, &credits)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "deposit_credits")
+            .add_attribute("address", info.sender))
+    }
+
+    fn withdraw_credits(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        amount: Option<Coin>,
+    ) -> Result<Response<C>, Self::Err> {
+        let mut credits = self
+            .credits
+            .may_load(deps.storage, &info.sender)?
+            .unwrap_or_default();
+
+        let withdrawn = match amount {
+            Some(amount) => {
+                let denom = Denom::Native(amount.denom);
+                take_balance(&mut credits, &denom, amount.amount)
+                    .ok_or_else(|| StdError::generic_err("not enough credits to withdraw."))?;
+                vec![Balance {
+                    denom,
+                    amount: amount.amount,
+                }]
+            }
+            None => std::mem::take(&mut credits),
+        };
+        if withdrawn.is_empty() {
+            return Err(ContractError::NothingToWithdraw {});
+        }
+        self.credits.save(deps.storage, &info.sender, &credits)?;
+
+        let msgs = withdrawn
+            .iter()
+            .map(|balance| transfer_msg(&info.sender, &balance.denom, balance.amount))
+            .collect::<StdResult<Vec<CosmosMsg<C>>>>()?;
+
+        Ok(Response::new()
+            .add_messages(msgs)
+            .add_attribute("action", "withdraw_credits")
+            .add_attribute("address", info.sender))
+    }
+
+    fn set_plans(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+        plans: Vec<Plan>,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_token_owner(deps.storage, &token_id, &info.sender)?;
+
+        if plans
+            .iter()
+            .any(|plan| plan.period == 0 || plan.price.amount.is_zero())
+        {
+            return Err(ContractError::Std(StdError::generic_err(
+                "plans must have a non-zero price and period.",
+            )));
+        }
+        self.plans.save(deps.storage, &token_id, &plans)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_plans")
+            .add_attribute("token_id", token_id)
+            .add_attribute("plans", plans.len().to_string()))
+    }
+
+    fn subscribe(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        plan: u32,
+    ) -> Result<Response<C>, Self::Err> {
+        let key = (token_id.as_str(), &info.sender);
+        let subscription = match self.subscriptions.may_load(deps.storage, key)? {
+            // extend an active subscription on its own terms
+            Some(mut subscription) if subscription.is_active(&env.block) => {
+                if subscription.plan_id != plan {
+                    return Err(ContractError::PlanMismatch { token_id, plan });
+                }
+                subscription.expires = subscription.expires.plus_seconds(subscription.plan.period);
+                subscription
+            }
+            _ => {
+                let plan_id = plan;
+                let plan = self
+                    .plans
+                    .may_load(deps.storage, &token_id)?
+                    .unwrap_or_default()
+                    .into_iter()
+                    .nth(plan as usize)
+                    .ok_or_else(|| ContractError::PlanNotFound {
+                        token_id: token_id.clone(),
+                        plan,
+                    })?;
+                Subscription {
+                    plan_id,
+                    period_start: env.block.time,
+                    expires: env.block.time.plus_seconds(plan.period),
+                    plan,
+                    requests: 0,
+                }
+            }
+        };
+
+        let received = must_pay(&info, &subscription.plan.price.denom)?;
+        if received != subscription.plan.price.amount {
+            return Err(ContractError::IncorrectPayment {
+                expected: subscription.plan.price.amount,
+                received,
+            });
+        }
+        self.credit_token(
+            deps.storage,
+            &token_id,
+            &Denom::Native(subscription.plan.price.denom.clone()),
+            received,
+        )?;
+        self.subscriptions.save(deps.storage, key, &subscription)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "subscribe")
+            .add_attribute("token_id", token_id)
+            .add_attribute("subscriber", info.sender.clone())
+            .add_attribute("expires", subscription.expires.to_string()))
+    }
+
+    fn unsubscribe(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response<C>, Self::Err> {
+        self.subscriptions
+            .remove(deps.storage, (&token_id, &info.sender));
+
+        Ok(Response::new()
+            .add_attribute("action", "unsubscribe")
+            .add_attribute("token_id", token_id)
+            .add_attribute("subscriber", info.sender))
+    }
+
+    fn set_pauses(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        minting: Option<bool>,
+        requests: Option<bool>,
+        responses: Option<bool>,
+        transfers: Option<bool>,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_role(deps.storage, &info.sender, Role::Admin)?;
+
+        let mut pauses = self.pauses.may_load(deps.storage)?.unwrap_or_default();
+        // record when responses stop and resume for slashing deadlines to leave out
+        if let Some(paused) = responses.filter(|paused| *paused != pauses.responses) {
+            let start = if paused {
+                Some(env.block.height)
+            } else {
+                self.response_pauses
+                    .keys(deps.storage, None, None, Order::Descending)
+                    .next()
+                    .transpose()?
+            };
+            let end = if paused { u64::MAX } else { env.block.height };
+            if let Some(start) = start {
+                self.response_pauses.save(deps.storage, start, &end)?;
+            }
+        }
+        pauses.minting = minting.unwrap_or(pauses.minting);
+        pauses.requests = requests.unwrap_or(pauses.requests);
+        pauses.responses = responses.unwrap_or(pauses.responses);
+        pauses.transfers = transfers.unwrap_or(pauses.transfers);
+        self.pauses.save(deps.storage, &pauses)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_pauses")
+            .add_attribute("minting", pauses.minting.to_string())
+            .add_attribute("requests", pauses.requests.to_string())
+            .add_attribute("responses", pauses.responses.to_string())
+            .add_attribute("transfers", pauses.transfers.to_string()))
+    }
+
+    fn suspend(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        suspended: bool,
+    ) -> Result<Response<C>, Self::Err> {
+        let moderated = self.has_role(deps.storage, &info.sender, Role::Moderator)?;
+        if !moderated {
+            self.assert_token_owner(deps.storage, &token_id, &info.sender)?;
+            // owners can neither lift nor take over a moderator's suspension, while one set by
+            // a previous owner passes on with the token
+            let suspension = self.suspended.may_load(deps.storage, &token_id)?;
+            if suspension.map_or(false, |suspension| suspension.moderated) {
+                return Err(ContractError::Unauthorized {});
+            }
+        }
+
+        // record when the project stops and resumes for slashing deadlines to leave out
+        if suspended != self.suspended.has(deps.storage, &token_id) {
+            let start = if suspended {
+                Some(env.block.height)
+            } else {
+                self.suspensions
+                    .prefix(&token_id)
+                    .keys(deps.storage, None, None, Order::Descending)
+                    .next()
+                    .transpose()?
+            };
+            let end = if suspended {
+                u64::MAX
+            } else {
+                env.block.height
+            };
+            if let Some(start) = start {
+                self.suspensions
+                    .save(deps.storage, (&token_id, start), &end)?;
+            }
+        }
+
+        let action = if suspended {
+            let suspension = Suspension {
+                suspended_by: info.sender,
+                moderated,
+            };
+            self.suspended.save(deps.storage, &token_id, &suspension)?;
+            "suspend"
+        } else {
+            self.suspended.remove(deps.storage, &token_id);
+            "unsuspend"
+        };
+        Ok(Response::new()
+            .add_attribute("action", action)
+            .add_attribute("token_id", token_id))
+    }
+
+    fn set_role(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        address: String,
+        role: Role,
+        granted: bool,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_role(deps.storage, &info.sender, Role::Admin)?;
+
+        let address = deps.api.addr_validate(&address)?;
+        let mut roles = self
+            .roles
+            .may_load(deps.storage, &address)?
+            .unwrap_or_default();
+        roles.retain(|r| *r != role);
+        if granted {
+            roles.push(role.clone());
+        }
+        if roles.is_empty() {
+            self.roles.remove(deps.storage, &address);
+        } else {
+            self.roles.save(deps.storage, &address, &roles)?;
+        }
+
+        let action = if granted { "grant_role" } else { "revoke_role" };
+        Ok(Response::new()
+            .add_attribute("action", action)
+            .add_attribute("address", address)
+            .add_attribute("role", role.to_string()))
+    }
+
+    fn set_rate_limits(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+        per_address: Option<RateLimit>,
+        per_project: Option<RateLimit>,
+        exempt: Vec<String>,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_token_owner(deps.storage, &token_id, &info.sender)?;
+
+        if per_address.is_none() && per_project.is_none() {
+            self.rate_limits.remove(deps.storage, &token_id);
+        } else {
+            let exempt = exempt
+                .iter()
+                .map(|address| deps.api.addr_validate(address))
+                .collect::<StdResult<Vec<_>>>()?;
+            let limits = RateLimits {
+                per_address,
+                per_project,
+                exempt,
+            };
+            self.rate_limits.save(deps.storage, &token_id, &limits)?;
+        }
+
+        Ok(Response::new()
+            .add_attribute("action", "set_rate_limits")
+            .add_attribute("token_id", token_id))
+    }
+
+    fn set_private(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+        private: bool,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_token_owner(deps.storage, &token_id, &info.sender)?;
+
+        if private {
+            self.private_projects
+                .save(deps.storage, &token_id, &Empty {})?;
+        } else {
+            self.private_projects.remove(deps.storage, &token_id);
+        }
+
+        Ok(Response::new()
+            .add_attribute("action", "set_private")
+            .add_attribute("token_id", token_id)
+            .add_attribute("private", private.to_string()))
+    }
+
+    fn issue_license(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+        holder: String,
+        expires: Option<Expiration>,
+        uses: Option<u32>,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_token_owner(deps.storage, &token_id, &info.sender)?;
+
+        let holder = deps.api.addr_validate(&holder)?;
+        let license = License {
+            holder,
+            expires: expires.unwrap_or_default(),
+            uses,
+        };
+        self.licenses
+            .save(deps.storage, (&token_id, &license.holder), &license)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "issue_license")
+            .add_attribute("token_id", token_id)
+            .add_attribute("holder", license.holder))
+    }
+
+    fn revoke_license(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+        holder: String,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_token_owner(deps.storage, &token_id, &info.sender)?;
+
+        let holder = deps.api.addr_validate(&holder)?;
+        self.licenses.remove(deps.storage, (&token_id, &holder))?;
+
+        Ok(Response::new()
+            .add_attribute("action", "revoke_license")
+            .add_attribute("token_id", token_id)
+            .add_attribute("holder", holder))
+    }
+
+    fn set_license_offer(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+        offer: Option<LicenseOffer>,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_token_owner(deps.storage, &token_id, &info.sender)?;
+
+        match &offer {
+            Some(offer) => self.license_offers.save(deps.storage, &token_id, offer)?,
+            None => self.license_offers.remove(deps.storage, &token_id),
+        }
+
+        Ok(Response::new()
+            .add_attribute("action", "set_license_offer")
+            .add_attribute("token_id", token_id))
+    }
+
+    fn buy_license(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response<C>, Self::Err> {
+        self.cw721.tokens.load(deps.storage, &token_id)?;
+        let offer = self
+            .license_offers
+            .may_load(deps.storage, &token_id)?
+            .ok_or_else(|| ContractError::NoLicenseOffer {
+                token_id: token_id.clone(),
+            })?;
+        let received = must_pay(&info, &offer.price.denom)?;
+        if received != offer.price.amount {
+            return Err(ContractError::IncorrectPayment {
+                expected: offer.price.amount,
+                received,
+            });
+        }
+        // a license is only bought again once it runs out
+        let current = self
+            .licenses
+            .may_load(deps.storage, (&token_id, &info.sender))?;
+        if current.map_or(false, |license| license.is_valid(&env.block)) {
+            return Err(ContractError::LicenseActive { token_id });
+        }
+        self.credit_token(
+            deps.storage,
+            &token_id,
+            &Denom::Native(offer.price.denom),
+            received,
+        )?;
+
+        let license = License {
+            holder: info.sender,
+            expires: offer
+                .duration
+                .map(|duration| duration.after(&env.block))
+                .unwrap_or_default(),
+            uses: offer.uses,
+        };
+        self.licenses
+            .save(deps.storage, (&token_id, &license.holder), &license)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "buy_license")
+            .add_attribute("token_id", token_id)
+            .add_attribute("holder", license.holder))
+    }
+
+    fn list_nft(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        price: Coin,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_token_owner(deps.storage, &token_id, &info.sender)?;
+        if price.amount.is_zero() {
+            return Err(ContractError::Std(StdError::generic_err(
+                "price must be non-zero.",
+            )));
+        }
+
+        // hold the token so it cannot move until it is bought or delisted
+        self.hand_over(deps.storage, &token_id, env.contract.address)?;
+        let listing = Listing {
+            seller: info.sender,
+            price,
+        };
+        self.listings.save(deps.storage, &token_id, &listing)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "list_nft")
+            .add_attribute("seller", listing.seller)
+            .add_attribute("token_id", token_id)
+            .add_attribute("price", listing.price.to_string()))
+    }
+
+    fn delist_nft(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response<C>, Self::Err> {
+        let listing = self.load_listing(deps.storage, &token_id)?;
+        if listing.seller != info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+        self.listings.remove(deps.storage, &token_id)?;
+        self.hand_over(deps.storage, &token_id, listing.seller)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "delist_nft")
+            .add_attribute("seller", info.sender)
+            .add_attribute("token_id", token_id))
+    }
+
+    fn buy_nft(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response<C>, Self::Err> {
+        let listing = self.load_listing(deps.storage, &token_id)?;
+        if listing.seller == info.sender {
+            return Err(ContractError::Std(StdError::generic_err(
+                "sellers cannot buy their own listing.",
+            )));
+        }
+        let received = must_pay(&info, &listing.price.denom)?;
+        if received != listing.price.amount {
+            return Err(ContractError::IncorrectPayment {
+                expected: listing.price.amount,
+                received,
+            });
+        }
+        self.listings.remove(deps.storage, &token_id)?;
+        let token = self.hand_over(deps.storage, &token_id, info.sender.clone())?;
+        // fees the token earned while held stay with the seller
+        let earned = self
+            .token_balances
+            .may_load(deps.storage, &token_id)?
+            .unwrap_or_default();
+        self.token_balances.remove(deps.storage, &token_id);
+        for balance in earned {
+            self.credit(
+                deps.storage,
+                &listing.seller,
+                &balance.denom,
+                balance.amount,
+            )?;
+        }
+
+        // royalty and market fee come out of the price when someone receives them, the
+        // seller gets the rest
+        let price = listing.price.amount;
+        let denom = Denom::Native(listing.price.denom.clone());
+        let metadata = token.extension.unwrap_or_default();
+        let mut res = Response::new();
+        let royalty = match metadata.royalty_payment_address {
+            Some(address) => {
+                let royalty =
+                    price.multiply_ratio(metadata.royalty_percentage.unwrap_or_default(), 100u128);
+                if !royalty.is_zero() {
+                    res = res.add_message(transfer_msg(&address, &denom, royalty)?);
+                }
+                royalty
+            }
+            None => Uint128::zero(),
+        };
+        let fee = match self.treasury(deps.storage)? {
+            Some(treasury) => {
+                let fee = price * self.market_fee.may_load(deps.storage)?.unwrap_or_default();
+                self.credit(deps.storage, &treasury, &denom, fee)?;
+                fee
+            }
+            None => Uint128::zero(),
+        };
+        let proceeds = price
+            .checked_sub(royalty + fee)
+            .map_err(|_| StdError::generic_err("royalty and market fee exceed the price."))?;
+        if !proceeds.is_zero() {
+            res = res.add_message(transfer_msg(&listing.seller, &denom, proceeds)?);
+        }
+
+        Ok(res
+            .add_attribute("action", "buy_nft")
+            .add_attribute("buyer", info.sender)
+            .add_attribute("seller", listing.seller)
+            .add_attribute("token_id", token_id)
+            .add_attribute("royalty", royalty)
+            .add_attribute("fee", fee))
+    }
+
+    fn set_market_fee(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        fee: Decimal,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_role(deps.storage, &info.sender, Role::Treasurer)?;
+
+        if fee > Decimal::one() {
+            return Err(ContractError::Std(StdError::generic_err(
+                "market fee cannot exceed one.",
+            )));
+        }
+        self.market_fee.save(deps.storage, &fee)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_market_fee")
+            .add_attribute("fee", fee.to_string()))
+    }
+
+    fn set_max_royalty_percentage(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        percentage: u64,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_role(deps.storage, &info.sender, Role::Admin)?;
+
+        if percentage > 100 {
+            return Err(ContractError::RoyaltyTooHigh { max: 100 });
+        }
+        self.max_royalty_percentage
+            .save(deps.storage, &percentage)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_max_royalty_percentage")
+            .add_attribute("percentage", percentage.to_string()))
+    }
+
+    fn set_lineage_share(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        share: Decimal,
+    ) -> Result<Response<C>, Self::Err> {
+        self.assert_role(deps.storage, &info.sender, Role::Treasurer)?;
+
+        if share > Decimal::one() {
+            return Err(ContractError::InvalidLineageShare {});
+        }
+        self.lineage_share.save(deps.storage, &share)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_lineage_share")
+            .add_attribute("share", share.to_string()))
+    }
+
+    fn fork(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        code: Option<String>,
+    ) -> Result<Response<C>, Self::Err> {
+        let parent: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
+        // forking a private project copies its code, so it takes a license as well
+        self.assert_licensed(deps.storage, &env, &parent, &token_id, &info.sender)?;
+        let parent_metadata = parent.extension.unwrap_or_default();
+        let metadata = Metadata {
+            title: parent_metadata.title,
+            description: parent_metadata.description,
+            code: code.unwrap_or(parent_metadata.code),
+            children: parent_metadata.children,
+            parent: Some(token_id.clone()),
+            ..Metadata::default()
+        };
+
+        let fork_id = self.cw721.token_count(deps.storage)?.to_string();
+        self.forks
+            .save(deps.storage, (&token_id, &fork_id), &Empty {})?;
+        // queue forks of undescribed projects for describers, like minted ones
+        if metadata.title.is_none() || metadata.description.is_none() {
+            let mut incomplete_projects = self
+                .incomplete_projects
+                .may_load(deps.storage)?
+                .unwrap_or(IncompleteProjectsResponse { pids: Vec::new() });
+            incomplete_projects.pids.push(fork_id);
+            self.incomplete_projects
+                .save(deps.storage, &incomplete_projects)?;
+        }
+        let owner = info.sender.to_string();
+        let res = self.mint_anyone(deps, info, owner, parent.token_uri, Some(metadata))?;
+
+        Ok(res.add_attribute("forked_from", token_id))
+    }
+
+    fn withdraw(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Response<C>, Self::Err> {
+        let (balances, token_ids) =
+            self.claimable(deps.storage, &info.sender, start_after, limit)?;
+        if balances.is_empty() {
+            return Err(ContractError::NothingToWithdraw {});
+        }
+
+        self.balances.remove(deps.storage, &info.sender);
+        for token_id in token_ids {
+            self.token_balances.remove(deps.storage, &token_id);
+        }
+
+        let msgs = balances
+            .iter()
+            .map(|balance| transfer_msg(&info.sender, &balance.denom, balance.amount))
+            .collect::<StdResult<Vec<CosmosMsg<C>>>>()?;
+
+        Ok(Response::new()
+            .add_messages(msgs)
+            .add_attribute("action", "withdraw")
+            .add_attribute("recipient", info.sender))
+    }
+}
+
+impl<'a, C, E, Q> Gateway721Contract<'a, Extension, C, E, Q>
+where
+    C: CustomMsg,
+    E: CustomMsg,
+    Q: CustomMsg,
+{
+    #[allow(clippy::too_many_arguments)]
+    fn create_task(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+        requester: Addr,
+        token_id: String,
+        input: String,
+        not_before: Option<Expiration>,
+        payment: Option<Price>,
+    ) -> Result<Response<C>, ContractError> {
+        self.assert_rate_limits(deps.storage, env, &token_id, &requester)?;
+        let task_id = self.push_task(
+            deps.storage,
+            env,
+            requester.clone(),
+            &token_id,
+            input,
+            not_before,
+            payment,
+        )?;
+
+        Ok(Response::new()
+            .add_attribute("action", "request")
+            .add_attribute("requester", requester)
+            .add_attribute("token_id", token_id)
+            .add_attribute("task_id", task_id))
+    }
+
+    /// Submits an output directly or through an attestation, returning the executor it is
+    /// credited to and the messages to dispatch once its task is finalized
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+    fn respond(
+        &self,
+        deps: &mut DepsMut,
+        env: &Env,
+        sender: Addr,
+        token_id: &str,
+        task_id: &str,
+        output: String,
+        attestation: Option<Attestation>,
+    ) -> Result<(Addr, Option<Vec<CosmosMsg<C>>>), ContractError> {
+        // attested outputs are credited to the signing executor, whoever relays them
+        let executor = match attestation {
+            Some(attestation) => {
+                self.verify_attestation(deps, env, token_id, task_id, &output, attestation)?
+            }
+            None => sender,
+        };
+        self.assert_project_executor(deps.storage, token_id, &executor)?;
+        if self.commit_windows.has(deps.storage, token_id) {
+            return Err(ContractError::CommitRevealRequired {
+                token_id: token_id.to_string(),
+            });
+        }
+
+        let outcome =
+            self.submit_output(deps.storage, env, token_id, task_id, &executor, output)?;
+        Ok((executor, outcome))
+    }
+
+    /// Matches native funds attached to a batch against the price of one request to each project,
+    /// or pays each request like a request without funds when none are attached
+    fn batch_payments(
+        &self,
+        storage: &mut dyn Storage,
+        env: &Env,
+        requester: &Addr,
+        token_ids: &[&str],
+        funds: &[Coin],
+    ) -> Result<Vec<(Option<Price>, PaymentSource)>, ContractError> {
+        if funds.is_empty() {
+            return token_ids
+                .iter()
+                .map(|token_id| self.prepaid_payment(storage, env, requester, token_id))
+                .collect();
+        }
+
+        let mut remaining = funds.to_vec();
+        let mut payments = Vec::with_capacity(token_ids.len());
+        for token_id in token_ids {
+            let prices = self.prices.may_load(storage, token_id)?.unwrap_or_default();
+            if prices.is_empty() {
+                payments.push((None, PaymentSource::Funds));
+                continue;
+            }
+            // pay with the first accepted denom that still covers the price
+            let (index, price) = prices
+                .into_iter()
+                .find_map(|price| {
+                    let index = remaining.iter().position(|coin| {
+                        price.denom == Denom::Native(coin.denom.clone())
+                            && coin.amount >= price.amount
+                    })?;
+                    Some((index, price))
+                })
+                .ok_or(PaymentError::NoFunds {})?;
+            remaining[index].amount -= price.amount;
+            payments.push((Some(price), PaymentSource::Funds));
+        }
+
+        if remaining.iter().any(|coin| !coin.amount.is_zero()) {
+            return Err(ContractError::Std(StdError::generic_err(
+                "attached funds exceed the batch prices.",
+            )));
+        }
+        Ok(payments)
+    }
+
+    /// Appends a task to the project and holds its payment, returning the task id
+    #[allow(clippy::too_many_arguments)]
+    fn push_task(
+        &self,
+        storage: &mut dyn Storage,
+        env: &Env,
+        requester: Addr,
+        token_id: &str,
+        input: String,
+        not_before: Option<Expiration>,
+        payment: Option<Price>,
+    ) -> Result<String, ContractError> {
+        self.assert_requestable(storage, env, token_id, &requester)?;
+        let mut token: TokenInfo<Extension> = self.cw721.tokens.load(storage, token_id)?;
+        if let Some(mut license) =
+            self.assert_licensed(storage, env, &token, token_id, &requester)?
+        {
+            if let Some(uses) = license.uses.as_mut() {
+                *uses -= 1;
+                self.licenses
+                    .save(storage, (token_id, &requester), &license)?;
+            }
+        }
+        let new_tid;
+        let children = token
+            .extension
+            .as_ref()
+            .and_then(|metadata| metadata.children.clone())
+            .unwrap_or_default();
+
+        if let Some(ref mut metadata) = token.extension {
+            // Initialize the tasks vector if it does not exist
+            if metadata.tasks.is_none() {
+                metadata.tasks = Some(Vec::new());
+            }
+
+            // Generate a new id for the task
+            new_tid = (metadata.tasks.as_ref().unwrap().len()).to_string();
+
+            // Push the new task to the tasks vector
+            if let Some(ref mut tasks) = metadata.tasks {
+                tasks.push(Task {
+                    tid: new_tid.clone(),
+                    requester: Some(requester.clone()),
+                    requested_at: Some(env.block.height),
+                    not_before,
+                    input,
+                    output: None,
+                    ..Task::default()
+                });
+            } else {
+                return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+                    "tasks are not valid.",
+                )));
+            }
+        } else {
+            return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+                "token extension is not valid.",
+            )));
+        }
+
+        // Save the updated token back to storage
+        self.cw721.tokens.save(storage, token_id, &token)?;
+
+        // Hold the payment until the task is responded or cancelled
+        if let Some(price) = payment {
+            self.escrows.save(
+                storage,
+                (token_id, &new_tid),
+                &Escrow {
+                    payer: requester.clone(),
+                    denom: price.denom,
+                    amount: price.amount,
+                },
+            )?;
+        }
+
+        if !children.is_empty() {
+            self.fan_out(
+                storage, env, requester, token_id, &new_tid, &children, not_before,
+            )?;
+        }
+
+        Ok(new_tid)
+    }
+
+    /// Fails if `requester` cannot request the project or any of its children, before
+    /// anything is written so that callers can recover from it
+    fn assert_requestable(
+        &self,
+        storage: &dyn Storage,
+        env: &Env,
+        token_id: &str,
+        requester: &Addr,
+    ) -> Result<(), ContractError> {
+        self.assert_not_suspended(storage, token_id)?;
+        let token: TokenInfo<Extension> = self.cw721.tokens.load(storage, token_id)?;
+        self.assert_licensed(storage, env, &token, token_id, requester)?;
+        let Some(metadata) = token.extension else {
+            return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+                "token extension is not valid.",
+            )));
+        };
+
+        // children are paid through the composite, so they must not charge themselves and
+        // their executors share the executor cut of the composite payment
+        for child in metadata.children.unwrap_or_default() {
+            if !self
+                .prices
+                .may_load(storage, &child)?
+                .unwrap_or_default()
+                .is_empty()
+            {
+                return Err(ContractError::PricedChild { token_id: child });
+            }
+            self.assert_requestable(storage, env, &child, requester)?;
+        }
+        Ok(())
+    }
+
+    /// Requests every child of a composite with the input of its task
+    #[allow(clippy::too_many_arguments)]
+    fn fan_out(
+        &self,
+        storage: &mut dyn Storage,
+        env: &Env,
+        requester: Addr,
+        token_id: &str,
+        task_id: &str,
+        children: &[String],
+        not_before: Option<Expiration>,
+    ) -> Result<(), ContractError> {
+        let token: TokenInfo<Extension> = self.cw721.tokens.load(storage, token_id)?;
+        let input = find_task(&token, task_id)?.input.clone();
+        self.fan_outs
+            .save(storage, (token_id, task_id), &vec![None; children.len()])?;
+
+        for (index, child) in children.iter().enumerate() {
+            let child_task_id = self.push_task(
+                storage,
+                env,
+                requester.clone(),
+                child,
+                input.clone(),
+                not_before,
+                None,
+            )?;
+            self.task_links.save(
+                storage,
+                (child, &child_task_id),
+                &TaskLink::Composite {
+                    token_id: token_id.to_string(),
+                    task_id: task_id.to_string(),
+                    index: index as u32,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Caps the royalty of a new project and sends it to the owner unless another address is set
+    fn check_royalty(
+        &self,
+        deps: Deps,
+        owner: &str,
+        extension: Extension,
+    ) -> Result<Extension, ContractError> {
+        let Some(mut metadata) = extension else {
+            return Ok(None);
+        };
+        let Some(percentage) = metadata.royalty_percentage else {
+            return Ok(Some(metadata));
+        };
+
+        let max = self
+            .max_royalty_percentage
+            .may_load(deps.storage)?
+            .unwrap_or(100);
+        if percentage > max {
+            return Err(ContractError::RoyaltyTooHigh { max });
+        }
+        let address = match metadata.royalty_payment_address {
+            Some(address) => address.to_string(),
+            None => owner.to_string(),
+        };
+        metadata.royalty_payment_address = Some(deps.api.addr_validate(&address)?);
+        Ok(Some(metadata))
+    }
+
+    /// Composites can only reference projects that already exist
+    fn assert_children(&self, storage: &dyn Storage, extension: &Extension) -> StdResult<()> {
+        let children = extension
+            .as_ref()
+            .and_then(|metadata| metadata.children.as_ref());
+        for child in children.into_iter().flatten() {
+            self.cw721.tokens.load(storage, child)?;
+        }
+        Ok(())
+    }
+
+    /// The contract owner and registered executors can respond to tasks, executors
+    /// must keep the minimum bond when staking is enabled
+    fn assert_executor(&self, storage: &dyn Storage, sender: &Addr) -> Result<(), ContractError> {
+        if self.has_role(storage, sender, Role::Admin)? {
+            return Ok(());
+        }
+        if !self.executors.has(storage, sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+        if let Some(config) = self.staking.may_load(storage)? {
+            let bonded = self
+                .stakes
+                .may_load(storage, sender)?
+                .unwrap_or_default()
+                .bonded;
+            if bonded < config.min_bond {
+                return Err(ContractError::InsufficientBond {
+                    min_bond: config.min_bond,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks an attestation against the executor's key, returning the executor
+    fn verify_attestation(
+        &self,
+        deps: &DepsMut,
+        env: &Env,
+        token_id: &str,
+        task_id: &str,
+        output: &str,
+        attestation: Attestation,
+    ) -> Result<Addr, ContractError> {
+        let executor = deps.api.addr_validate(&attestation.executor)?;
+        let key = self
+            .executor_keys
+            .may_load(deps.storage, &executor)?
+            .ok_or_else(|| ContractError::ExecutorKeyNotFound {
+                executor: executor.to_string(),
+            })?;
+
+        let mut token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, token_id)?;
+        let code_hash = sha256(
+            token
+                .extension
+                .as_ref()
+                .map(|metadata| metadata.code.as_str())
+                .unwrap_or_default(),
+        );
+        let task = find_task_mut(&mut token, task_id)?;
+        let hash = attestation_hash(
+            env.contract.address.as_str(),
+            token_id,
+            task_id,
+            &code_hash,
+            &input_hash(&task.input),
+            output,
+        );
+
+        let verified = match key.scheme {
+            KeyScheme::Secp256k1 => {
+                deps.api
+                    .secp256k1_verify(hash.as_slice(), &attestation.signature, &key.pubkey)
+            }
+            KeyScheme::Ed25519 => {
+                deps.api
+                    .ed25519_verify(hash.as_slice(), &attestation.signature, &key.pubkey)
+            }
+        }
+        .unwrap_or(false);
+        if !verified {
+            return Err(ContractError::InvalidAttestation {});
+        }
+        Ok(executor)
+    }
+
+    /// Only allowlisted executors can work on a project with an allowlist, registered
+    /// executors otherwise
+    fn assert_project_executor(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+        executor: &Addr,
+    ) -> Result<(), ContractError> {
+        match self.allowlists.may_load(storage, token_id)? {
+            Some(allowlist) if allowlist.contains(executor) => Ok(()),
+            Some(_) => Err(ContractError::Unauthorized {}),
+            None => self.assert_executor(storage, executor),
+        }
+    }
+
+    /// Fails while another executor holds an unexpired claim on the task
+    fn assert_unclaimed(
+        &self,
+        storage: &dyn Storage,
+        env: &Env,
+        token_id: &str,
+        task_id: &str,
+        executor: &Addr,
+    ) -> Result<(), ContractError> {
+        match self.claims.may_load(storage, (token_id, task_id))? {
+            Some(claim)
+                if claim.executor != *executor
+                    && !self.claim_expired(storage, env, token_id, &claim)? =>
+            {
+                Err(ContractError::TaskClaimed {
+                    task_id: task_id.to_string(),
+                    executor: claim.executor.to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether a claim ran out, leaving out the blocks its project could not be responded to
+    fn claim_expired(
+        &self,
+        storage: &dyn Storage,
+        env: &Env,
+        token_id: &str,
+        claim: &TaskClaim,
+    ) -> StdResult<bool> {
+        let halted = self.halted_blocks(storage, token_id, claim.claimed_at, env.block.height)?;
+        Ok(env.block.height > claim.expires_at + halted)
+    }
+
+    /// Blocks from `from` up to `to` during which responses were paused or the project was
+    /// suspended
+    fn halted_blocks(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+        from: u64,
+        to: u64,
+    ) -> StdResult<u64> {
+        let pauses = self.response_pauses.range(
+            storage,
+            None,
+            Some(Bound::exclusive(to)),
+            Order::Descending,
+        );
+        let suspensions = self.suspensions.prefix(token_id).range(
+            storage,
+            None,
+            Some(Bound::exclusive(to)),
+            Order::Descending,
+        );
+        Ok(overlap(pauses, from, to)? + overlap(suspensions, from, to)?)
+    }
+
+    /// Takes the slash ratio of an executor's bonded and unbonding stake and credits it
+    /// to the treasury, returning the slashed amount. Nothing is taken without a treasury to
+    /// receive it
+    fn slash(&self, storage: &mut dyn Storage, executor: &Addr) -> StdResult<Uint128> {
+        let Some(config) = self.staking.may_load(storage)? else {
+            return Ok(Uint128::zero());
+        };
+        let Some(treasury) = self.treasury(storage)? else {
+            return Ok(Uint128::zero());
+        };
+        let mut stake = self.stakes.may_load(storage, executor)?.unwrap_or_default();
+
+        let total = stake.bonded
+            + stake
+                .unbonding
+                .iter()
+                .map(|unbonding| unbonding.amount)
+                .sum::<Uint128>();
+        let amount = total * config.slash_ratio;
+
+        // bonded stake goes first, then the oldest unbonding entries
+        let mut remaining = amount;
+        let from_bond = remaining.min(stake.bonded);
+        stake.bonded -= from_bond;
+        remaining -= from_bond;
+        for unbonding in stake.unbonding.iter_mut() {
+            let taken = remaining.min(unbonding.amount);
+            unbonding.amount -= taken;
+            remaining -= taken;
+        }
+        stake
+            .unbonding
+            .retain(|unbonding| !unbonding.amount.is_zero());
+        stake.slashed += amount;
+        self.stakes.save(storage, executor, &stake)?;
+
+        self.credit(storage, &treasury, &Denom::Native(config.denom), amount)?;
+        Ok(amount)
+    }
+
+    /// The revenue share treasury, or the contract owner when unset
+    fn treasury(&self, storage: &dyn Storage) -> StdResult<Option<Addr>> {
+        match self.revenue_share.may_load(storage)? {
+            Some(share) => Ok(Some(share.treasury_address)),
+            None => Ok(cw_ownable::get_ownership(storage)?.owner),
+        }
+    }
+
+    /// Last block height accepting commitments for a task, if its project has a commit window
+    pub(crate) fn commit_deadline(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+        task: &Task,
+    ) -> StdResult<Option<u64>> {
+        let window = self.commit_windows.may_load(storage, token_id)?;
+        Ok(window.map(|blocks| task.requested_at.unwrap_or_default() + blocks - 1))
+    }
+
+    /// Records an executor's output and finalizes the task once its quorum agrees,
+    /// returning the messages to dispatch or `None` while the quorum is pending
+    fn submit_output(
+        &self,
+        storage: &mut dyn Storage,
+        env: &Env,
+        token_id: &str,
+        task_id: &str,
+        executor: &Addr,
+        output: String,
+    ) -> Result<Option<Vec<CosmosMsg<C>>>, ContractError> {
+        let mut token: TokenInfo<Extension> = self.cw721.tokens.load(storage, token_id)?;
+        let task = find_task_mut(&mut token, task_id)?;
+        if task.cancelled || task.output.is_some() {
+            return Err(ContractError::TaskClosed {
+                task_id: task_id.to_string(),
+            });
+        }
+        assert_due(task, env)?;
+        if self.fan_outs.has(storage, (token_id, task_id)) {
+            return Err(ContractError::CompositeTask {
+                task_id: task_id.to_string(),
+            });
+        }
+        self.assert_unclaimed(storage, env, token_id, task_id, executor)?;
+
+        let quorum = self.quorums.may_load(storage, token_id)?.unwrap_or(1);
+        if quorum <= 1 {
+            return self
+                .finalize_task(
+                    storage,
+                    env,
+                    token,
+                    token_id,
+                    task_id,
+                    output,
+                    vec![executor.clone()],
+                )
+                .map(Some);
+        }
+
+        let mut submissions = self
+            .submissions
+            .may_load(storage, (token_id, task_id))?
+            .unwrap_or_default();
+        if submissions.iter().any(|s| s.executor == *executor) {
+            return Err(ContractError::AlreadySubmitted {
+                executor: executor.to_string(),
+                task_id: task_id.to_string(),
+            });
+        }
+        let output_hash = sha256(&output);
+        submissions.push(Submission {
+            executor: executor.clone(),
+            output_hash: output_hash.clone(),
+            output: output.clone(),
+        });
+
+        let (agreeing, disagreeing): (Vec<_>, Vec<_>) = submissions
+            .iter()
+            .partition(|submission| submission.output_hash == output_hash);
+        if (agreeing.len() as u32) < quorum {
+            self.submissions
+                .save(storage, (token_id, task_id), &submissions)?;
+            return Ok(None);
+        }
+
+        // flag and slash executors that disagreed with the quorum
+        let agreeing = agreeing.into_iter().map(|s| s.executor.clone()).collect();
+        let flagged: Vec<Addr> = disagreeing
+            .into_iter()
+            .map(|s| s.executor.clone())
+            .collect();
+        for executor in &flagged {
+            self.slash(storage, executor)?;
+            self.record_stats(storage, executor, |stats| stats.disagreements += 1)?;
+        }
+        find_task_mut(&mut token, task_id)?.flagged = flagged;
+        self.submissions.remove(storage, (token_id, task_id));
+
+        self.finalize_task(storage, env, token, token_id, task_id, output, agreeing)
+            .map(Some)
+    }
+
+    /// Stores the output, pays the executors and delivers the output to the destination
+    #[allow(clippy::too_many_arguments)]
+    fn finalize_task(
+        &self,
+        storage: &mut dyn Storage,
+        env: &Env,
+        mut token: TokenInfo<Extension>,
+        token_id: &str,
+        task_id: &str,
+        output: String,
+        executors: Vec<Addr>,
+    ) -> Result<Vec<CosmosMsg<C>>, ContractError> {
+        let task = find_task_mut(&mut token, task_id)?;
+        task.output = Some(output.clone());
+        let task = task.clone();
+
+        // Save the updated token back to storage
+        self.cw721.tokens.save(storage, token_id, &token)?;
+        self.claims.remove(storage, (token_id, task_id));
+        // commitments left unrevealed no longer matter
+        let committed = self
+            .commitments
+            .prefix((token_id, task_id))
+            .keys(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for executor in committed {
+            self.commitments
+                .remove(storage, (token_id, task_id, &executor));
+        }
+
+        // release the payment to the fee recipients
+        if let Some(escrow) = self.escrows.may_load(storage, (token_id, task_id))? {
+            self.escrows.remove(storage, (token_id, task_id));
+            self.settle_escrow(storage, token_id, &executors, escrow)?;
+        }
+        // the executors of a composite are those of its children, already counted for them
+        let composite = token
+            .extension
+            .as_ref()
+            .and_then(|metadata| metadata.children.as_ref())
+            .map_or(false, |children| !children.is_empty());
+        let latency = env.block.height - task.requested_at.unwrap_or(env.block.height);
+        let mut points: Vec<&Addr> = vec![];
+        if !composite {
+            for executor in &executors {
+                self.record_stats(storage, executor, |stats| {
+                    stats.completed += 1;
+                    stats.total_latency_blocks += latency;
+                })?;
+            }
+            points.extend(&executors);
+        }
+        let owner = self.beneficial_owner(storage, token_id, &token)?;
+        points.push(&owner);
+        self.add_reward_points(storage, &points)?;
+
+        // send `output` to destination address
+        let mut msgs = vec![];
+        if let Some(dest) = token.extension.and_then(|metadata| metadata.destination) {
+            let msg = Gateway721ReceiveMsg {
+                version: RECEIVE_MSG_VERSION,
+                token_id: token_id.to_string(),
+                task_id: task_id.to_string(),
+                requester: task
+                    .requester
+                    .as_ref()
+                    .map(|requester| requester.to_string())
+                    .unwrap_or_default(),
+                input_hash: input_hash(&task.input),
+                output,
+            };
+            msgs.push(msg.into_cosmos_msg(dest)?);
+        }
+        if let Some(link) = self.task_links.may_load(storage, (token_id, task_id))? {
+            self.task_links.remove(storage, (token_id, task_id));
+            msgs.extend(self.follow_link(storage, env, link, token_id, &task, &executors)?);
+        }
+        Ok(msgs)
+    }
+
+    /// Moves on whatever was waiting on a finalized task
+    fn follow_link(
+        &self,
+        storage: &mut dyn Storage,
+        env: &Env,
+        link: TaskLink,
+        token_id: &str,
+        task: &Task,
+        executors: &[Addr],
+    ) -> Result<Vec<CosmosMsg<C>>, ContractError> {
+        let output = task.output.clone().unwrap_or_default();
+        match link {
+            TaskLink::Pipeline { run_id } => {
+                let mut run = self.pipeline_runs.load(storage, run_id)?;
+                let pipeline = self.pipelines.load(storage, run.pipeline_id)?;
+                run.stage += 1;
+
+                // request the next stage with the output of this one
+                if let Some(stage) = pipeline.stages.get(run.stage as usize) {
+                    // a stage that cannot be requested ends the run rather than blocking
+                    // this task, refunding the stages left
+                    if let Err(err) =
+                        self.assert_requestable(storage, env, &stage.token_id, &run.requester)
+                    {
+                        run.failure = Some(err.to_string());
+                        let msgs = self.refund_stages(storage, &run, run.stage as usize)?;
+                        run.payments.truncate(run.stage as usize);
+                        run.sources.truncate(run.stage as usize);
+                        self.pipeline_runs.save(storage, run_id, &run)?;
+                        return Ok(msgs);
+                    }
+                    let payment = run.payments[run.stage as usize].clone();
+                    self.request_stage(
+                        storage,
+                        env,
+                        run_id,
+                        &run,
+                        stage,
+                        stage.input.apply(&output),
+                        payment,
+                    )?;
+                    return Ok(vec![]);
+                }
+
+                self.pipeline_runs.remove(storage, run_id);
+                let Some(dest) = pipeline.destination else {
+                    return Ok(vec![]);
+                };
+                let msg = Gateway721ReceiveMsg {
+                    version: RECEIVE_MSG_VERSION,
+                    token_id: token_id.to_string(),
+                    task_id: task.tid.clone(),
+                    requester: run.requester.to_string(),
+                    input_hash: input_hash(&task.input),
+                    output,
+                };
+                Ok(vec![msg.into_cosmos_msg(dest)?])
+            }
+            TaskLink::Composite {
+                token_id: parent_id,
+                task_id: parent_task_id,
+                index,
+            } => {
+                // the composite task was cancelled meanwhile
+                let Some(mut outputs) = self
+                    .fan_outs
+                    .may_load(storage, (&parent_id, &parent_task_id))?
+                else {
+                    return Ok(vec![]);
+                };
+                outputs[index as usize] = Some(output);
+                let mut child_executors = self
+                    .fan_out_executors
+                    .may_load(storage, (&parent_id, &parent_task_id))?
+                    .unwrap_or_default();
+                for executor in executors {
+                    if !child_executors.contains(executor) {
+                        child_executors.push(executor.clone());
+                    }
+                }
+                if outputs.iter().any(Option::is_none) {
+                    self.fan_outs
+                        .save(storage, (&parent_id, &parent_task_id), &outputs)?;
+                    self.fan_out_executors.save(
+                        storage,
+                        (&parent_id, &parent_task_id),
+                        &child_executors,
+                    )?;
+                    return Ok(vec![]);
+                }
+
+                // every child is done, the composite output is the array of their outputs
+                // and its executors are those of the children
+                self.fan_outs.remove(storage, (&parent_id, &parent_task_id));
+                self.fan_out_executors
+                    .remove(storage, (&parent_id, &parent_task_id));
+                let outputs: Vec<String> = outputs.into_iter().flatten().collect();
+                let parent = self.cw721.tokens.load(storage, &parent_id)?;
+                self.finalize_task(
+                    storage,
+                    env,
+                    parent,
+                    &parent_id,
+                    &parent_task_id,
+                    to_json_string(&outputs)?,
+                    child_executors,
+                )
+            }
+        }
+    }
+
+    /// Creates the task of the current stage of a pipeline run, linked back to the run
+    #[allow(clippy::too_many_arguments)]
+    fn request_stage(
+        &self,
+        storage: &mut dyn Storage,
+        env: &Env,
+        run_id: u64,
+        run: &PipelineRun,
+        stage: &PipelineStage,
+        input: String,
+        payment: Option<Price>,
+    ) -> Result<String, ContractError> {
+        let task_id = self.push_task(
+            storage,
+            env,
+            run.requester.clone(),
+            &stage.token_id,
+            input,
+            None,
+            payment,
+        )?;
+        self.task_links.save(
+            storage,
+            (&stage.token_id, &task_id),
+            &TaskLink::Pipeline { run_id },
+        )?;
+        self.pipeline_runs.save(storage, run_id, run)?;
+        Ok(task_id)
+    }
+
+    fn record_stats(
+        &self,
+        storage: &mut dyn Storage,
+        executor: &Addr,
+        record: impl FnOnce(&mut ExecutorStats),
+    ) -> StdResult<()> {
+        let mut stats = self
+            .executor_stats
+            .may_load(storage, executor)?
+            .unwrap_or_default();
+        // registered executors move to the rank of their new stats
+        let registered = self.executors.has(storage, executor);
+        if registered {
+            let (reliability, completed) = stats.rank();
+            self.executor_ranks
+                .remove(storage, (reliability, completed, executor));
+        }
+        record(&mut stats);
+        if registered {
+            let (reliability, completed) = stats.rank();
+            self.executor_ranks
+                .save(storage, (reliability, completed, executor), &Empty {})?;
+        }
+        self.executor_stats.save(storage, executor, &stats)
+    }
+
+    /// Adds an executor to the registry and its ranking, or removes it from both
+    fn set_registered(
+        &self,
+        storage: &mut dyn Storage,
+        executor: &Addr,
+        registered: bool,
+    ) -> StdResult<()> {
+        let stats = self
+            .executor_stats
+            .may_load(storage, executor)?
+            .unwrap_or_default();
+        let (reliability, completed) = stats.rank();
+        let key = (reliability, completed, executor);
+        if registered {
+            self.executors.save(storage, executor, &Empty {})?;
+            self.executor_ranks.save(storage, key, &Empty {})
+        } else {
+            self.executors.remove(storage, executor);
+            self.executor_ranks.remove(storage, key);
+            Ok(())
+        }
+    }
+
+    /// Counts a completed task for each address towards the next rewards distribution
+    fn add_reward_points(&self, storage: &mut dyn Storage, addresses: &[&Addr]) -> StdResult<()> {
+        for address in addresses {
+            self.reward_points
+                .update(storage, address, |points| -> StdResult<_> {
+                    Ok(points.unwrap_or_default() + 1)
+                })?;
+        }
+        let total = self
+            .reward_points_total
+            .may_load(storage)?
+            .unwrap_or_default();
+        self.reward_points_total
+            .save(storage, &(total + addresses.len() as u64))
+    }
+
+    /// Splits a released payment between the token, the executors and the treasury
+    fn settle_escrow(
+        &self,
+        storage: &mut dyn Storage,
+        token_id: &str,
+        executors: &[Addr],
+        escrow: Escrow,
+    ) -> StdResult<()> {
+        let Some(share) = self.revenue_share.may_load(storage)? else {
+            return self.credit_lineage(storage, token_id, &escrow.denom, escrow.amount);
+        };
+
+        // agreeing executors share the executor cut evenly, the executors of its children for
+        // a composite, and leave it to the owner when there are none
+        let per_executor = if executors.is_empty() {
+            Uint128::zero()
+        } else {
+            (escrow.amount * share.executor).multiply_ratio(1u128, executors.len() as u128)
+        };
+        let treasury_amount = escrow.amount * share.treasury;
+        // the owner also gets the rounding remainder
+        let owner_amount =
+            escrow.amount - per_executor * Uint128::from(executors.len() as u128) - treasury_amount;
+
+        self.credit_lineage(storage, token_id, &escrow.denom, owner_amount)?;
+        for executor in executors {
+            self.credit(storage, executor, &escrow.denom, per_executor)?;
+        }
+        self.credit(
+            storage,
+            &share.treasury_address,
+            &escrow.denom,
+            treasury_amount,
+        )
+    }
+
+    pub(crate) fn credit(
+        &self,
+        storage: &mut dyn Storage,
+        address: &Addr,
+        denom: &Denom,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        if amount.is_zero() {
+            return Ok(());
+        }
+        self.balances
+            .update(storage, address, |balances| -> StdResult<_> {
+                let mut balances = balances.unwrap_or_default();
+                add_balance(&mut balances, denom, amount);
+                Ok(balances)
+            })
+            .map(|_| ())
+    }
+
+    /// Credits a token, passing the lineage share of it on to the project it was forked from
+    fn credit_lineage(
+        &self,
+        storage: &mut dyn Storage,
+        token_id: &str,
+        denom: &Denom,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        let share = self.lineage_share.may_load(storage)?.unwrap_or_default();
+        let mut token_id = token_id.to_string();
+        let mut amount = amount;
+        for _ in 0..MAX_LINEAGE_DEPTH {
+            let Some(parent) = self.parent(storage, &token_id)? else {
+                break;
+            };
+            let passed_on = amount * share;
+            if passed_on.is_zero() {
+                break;
+            }
+            self.credit_token(storage, &token_id, denom, amount - passed_on)?;
+            token_id = parent;
+            amount = passed_on;
+        }
+        self.credit_token(storage, &token_id, denom, amount)
+    }
+
+    /// Whether `address` holds `role`, through ownership of the contract or the admin role
+    fn has_role(
+        &self,
+        storage: &dyn Storage,
+        address: &Addr,
+        role: Role,
+    ) -> Result<bool, ContractError> {
+        if cw_ownable::is_owner(storage, address)? {
+            return Ok(true);
+        }
+        let roles = self.roles.may_load(storage, address)?.unwrap_or_default();
+        Ok(roles.contains(&Role::Admin) || roles.contains(&role))
+    }
+
+    fn assert_role(
+        &self,
+        storage: &dyn Storage,
+        address: &Addr,
+        role: Role,
+    ) -> Result<(), ContractError> {
+        if !self.has_role(storage, address, role.clone())? {
+            return Err(ContractError::MissingRole { role });
+        }
+        Ok(())
+    }
+
+    /// Rejects messages whose operation is paused or whose project is suspended. Requests
+    /// check suspension as their tasks are created
+    fn assert_not_paused(
+        &self,
+        storage: &dyn Storage,
+        msg: &ExecuteMsg<Extension, E>,
+    ) -> Result<(), ContractError> {
+        let pauses = self.pauses.may_load(storage)?.unwrap_or_default();
+        let paused = match msg {
+            ExecuteMsg::Mint { .. } | ExecuteMsg::Fork { .. } => {
+                pauses.minting.then_some("minting")
+            }
+            ExecuteMsg::Request { .. }
+            | ExecuteMsg::Receive(_)
+            | ExecuteMsg::BatchRequest { .. }
+            | ExecuteMsg::RunPipeline { .. }
+            | ExecuteMsg::ScheduleRequest { .. }
+            | ExecuteMsg::TriggerSchedule { .. } => pauses.requests.then_some("requests"),
+            ExecuteMsg::Response { .. }
+            | ExecuteMsg::BatchResponse { .. }
+            | ExecuteMsg::CommitOutput { .. }
+            | ExecuteMsg::RevealOutput { .. }
+            | ExecuteMsg::ClaimTask { .. }
+            | ExecuteMsg::SlashExpiredClaim { .. }
+            | ExecuteMsg::SlashUnrevealed { .. } => pauses.responses.then_some("responses"),
+            ExecuteMsg::TransferNft { .. }
+            | ExecuteMsg::SendNft { .. }
+            | ExecuteMsg::ListNft { .. }
+            | ExecuteMsg::DelistNft { .. }
+            | ExecuteMsg::BuyNft { .. } => pauses.transfers.then_some("transfers"),
+            _ => None,
+        };
+        if let Some(operation) = paused {
+            return Err(ContractError::Paused {
+                operation: operation.to_string(),
+            });
+        }
+
+        let token_ids: Vec<&str> = match msg {
+            ExecuteMsg::Response { token_id, .. }
+            | ExecuteMsg::CommitOutput { token_id, .. }
+            | ExecuteMsg::RevealOutput { token_id, .. }
+            | ExecuteMsg::ClaimTask { token_id, .. }
+            | ExecuteMsg::SlashExpiredClaim { token_id, .. }
+            | ExecuteMsg::SlashUnrevealed { token_id, .. }
+            | ExecuteMsg::Fork { token_id, .. }
+            | ExecuteMsg::BuyLicense { token_id }
+            | ExecuteMsg::Subscribe { token_id, .. }
+            | ExecuteMsg::BuyNft { token_id } => vec![token_id],
+            ExecuteMsg::BatchResponse { items } => {
+                items.iter().map(|item| item.token_id.as_str()).collect()
+            }
+            _ => vec![],
+        };
+        for token_id in token_ids {
+            self.assert_not_suspended(storage, token_id)?;
+        }
+        Ok(())
+    }
+
+    fn assert_not_suspended(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+    ) -> Result<(), ContractError> {
+        if self.suspended.has(storage, token_id) {
+            return Err(ContractError::Suspended {
+                token_id: token_id.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn assert_token_owner(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+        sender: &Addr,
+    ) -> Result<(), ContractError> {
+        let token: TokenInfo<Extension> = self.cw721.tokens.load(storage, token_id)?;
+        if token.owner != *sender {
+            return Err(ContractError::Unauthorized {});
+        }
+        Ok(())
+    }
+
+    /// Private projects can only be used by their owner and holders of a valid license,
+    /// returning the license `address` uses if any
+    fn assert_licensed(
+        &self,
+        storage: &dyn Storage,
+        env: &Env,
+        token: &TokenInfo<Extension>,
+        token_id: &str,
+        address: &Addr,
+    ) -> Result<Option<License>, ContractError> {
+        if !self.private_projects.has(storage, token_id) || token.owner == *address {
+            return Ok(None);
+        }
+        self.licenses
+            .may_load(storage, (token_id, address))?
+            .filter(|license| license.is_valid(&env.block))
+            .map(Some)
+            .ok_or_else(|| ContractError::NoLicense {
+                token_id: token_id.to_string(),
+            })
+    }
+
+    fn load_listing(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+    ) -> Result<Listing, ContractError> {
+        self.listings
+            .may_load(storage, token_id)?
+            .ok_or_else(|| ContractError::NotListed {
+                token_id: token_id.to_string(),
+            })
+    }
+
+    /// Moves a token into or out of a listing, dropping its approvals
+    fn hand_over(
+        &self,
+        storage: &mut dyn Storage,
+        token_id: &str,
+        owner: Addr,
+    ) -> StdResult<TokenInfo<Extension>> {
+        let mut token: TokenInfo<Extension> = self.cw721.tokens.load(storage, token_id)?;
+        token.owner = owner;
+        token.approvals = vec![];
+        self.cw721.tokens.save(storage, token_id, &token)?;
+        Ok(token)
+    }
+
+    /// The owner of a token, its seller while the contract holds it for a listing
+    fn beneficial_owner(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+        token: &TokenInfo<Extension>,
+    ) -> StdResult<Addr> {
+        Ok(self
+            .listings
+            .may_load(storage, token_id)?
+            .map_or_else(|| token.owner.clone(), |listing| listing.seller))
+    }
+
+    /// The project a token was forked from, none once that project is burned
+    pub(crate) fn parent(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+    ) -> StdResult<Option<String>> {
+        let Some(token) = self.cw721.tokens.may_load(storage, token_id)? else {
+            return Ok(None);
+        };
+        Ok(token
+            .extension
+            .and_then(|metadata| metadata.parent)
+            .filter(|parent| self.cw721.tokens.has(storage, parent)))
+    }
+
+    pub(crate) fn credit_token(
+        &self,
+        storage: &mut dyn Storage,
+        token_id: &str,
+        denom: &Denom,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        if amount.is_zero() {
+            return Ok(());
+        }
+        self.token_balances
+            .update(storage, token_id, |balances| -> StdResult<_> {
+                let mut balances = balances.unwrap_or_default();
+                add_balance(&mut balances, denom, amount);
+                Ok(balances)
+            })
+            .map(|_| ())
+    }
+
+    /// Checks a new request of `requester` against the limits of the project
+    fn assert_rate_limits(
+        &self,
+        storage: &dyn Storage,
+        env: &Env,
+        token_id: &str,
+        requester: &Addr,
+    ) -> Result<(), ContractError> {
+        let Some(limits) = self.rate_limits.may_load(storage, token_id)? else {
+            return Ok(());
+        };
+        let token: TokenInfo<Extension> = self.cw721.tokens.load(storage, token_id)?;
+        if token.owner == *requester || limits.exempt.contains(requester) {
+            return Ok(());
+        }
+
+        let tasks = token
+            .extension
+            .and_then(|metadata| metadata.tasks)
+            .unwrap_or_default();
+        let own_tasks: Vec<&Task> = tasks
+            .iter()
+            .filter(|task| task.requester.as_ref() == Some(requester))
+            .collect();
+        if let Some(limit) = &limits.per_address {
+            check_rate_limit(env, token_id, limit, own_tasks)?;
+        }
+        if let Some(limit) = &limits.per_project {
+            check_rate_limit(env, token_id, limit, tasks.iter().collect())?;
+        }
+        Ok(())
+    }
+
+    /// Counts a request against the requester's active subscription, returning whether it
+    /// covers the request
+    fn use_subscription(
+        &self,
+        storage: &mut dyn Storage,
+        env: &Env,
+        requester: &Addr,
+        token_id: &str,
+    ) -> Result<bool, ContractError> {
+        let Some(mut subscription) = self
+            .subscriptions
+            .may_load(storage, (token_id, requester))?
+        else {
+            return Ok(false);
+        };
+        if !subscription.is_active(&env.block) {
+            return Ok(false);
+        }
+
+        subscription.roll(&env.block);
+        if subscription.remaining() == Some(0) {
+            return Err(ContractError::QuotaExceeded {
+                token_id: token_id.to_string(),
+            });
+        }
+        subscription.requests += 1;
+        self.subscriptions
+            .save(storage, (token_id, requester), &subscription)?;
+        Ok(true)
+    }
+
+    /// Pays a request sent without funds from the requester's subscription, or else from
+    /// their credits
+    fn prepaid_payment(
+        &self,
+        storage: &mut dyn Storage,
+        env: &Env,
+        requester: &Addr,
+        token_id: &str,
+    ) -> Result<(Option<Price>, PaymentSource), ContractError> {
+        if self.use_subscription(storage, env, requester, token_id)? {
+            let subscription = self.subscriptions.load(storage, (token_id, requester))?;
+            let source = PaymentSource::Subscription {
+                period_start: subscription.period_start,
+            };
+            return Ok((None, source));
+        }
+        let payment = self.credit_payment(storage, requester, token_id)?;
+        let source = match payment {
+            Some(_) => PaymentSource::Credits,
+            None => PaymentSource::Funds,
+        };
+        Ok((payment, source))
+    }
+
+    /// Gives the stages of a pipeline run from `from` on back the way they were paid, returning
+    /// the transfers refunding those paid with funds
+    fn refund_stages(
+        &self,
+        storage: &mut dyn Storage,
+        run: &PipelineRun,
+        from: usize,
+    ) -> Result<Vec<CosmosMsg<C>>, ContractError> {
+        let pipeline = self.pipelines.load(storage, run.pipeline_id)?;
+        let mut msgs = vec![];
+        for (index, payment) in run.payments.iter().enumerate().skip(from) {
+            match run.sources.get(index).cloned().unwrap_or_default() {
+                PaymentSource::Funds => {
+                    if let Some(price) = payment {
+                        msgs.push(transfer_msg(&run.requester, &price.denom, price.amount)?);
+                    }
+                }
+                PaymentSource::Credits => {
+                    if let Some(price) = payment {
+                        let mut credits = self
+                            .credits
+                            .may_load(storage, &run.requester)?
+                            .unwrap_or_default();
+                        add_balance(&mut credits, &price.denom, price.amount);
+                        self.credits.save(storage, &run.requester, &credits)?;
+                    }
+                }
+                PaymentSource::Subscription { period_start } => {
+                    // the quota is only given back within the period it was taken from
+                    let key = (pipeline.stages[index].token_id.as_str(), &run.requester);
+                    if let Some(mut subscription) = self.subscriptions.may_load(storage, key)? {
+                        if subscription.period_start == period_start && subscription.requests > 0 {
+                            subscription.requests -= 1;
+                            self.subscriptions.save(storage, key, &subscription)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(msgs)
+    }
+
+    /// Pays a request from the requester's credits with the first project price they cover
+    fn credit_payment(
+        &self,
+        storage: &mut dyn Storage,
+        requester: &Addr,
+        token_id: &str,
+    ) -> Result<Option<Price>, ContractError> {
+        let prices = self.prices.may_load(storage, token_id)?.unwrap_or_default();
+        if prices.is_empty() {
+            return Ok(None);
+        }
+
+        let mut credits = self
+            .credits
+            .may_load(storage, requester)?
+            .unwrap_or_default();
+        let price = prices
+            .into_iter()
+            .find(|price| take_balance(&mut credits, &price.denom, price.amount).is_some())
+            .ok_or_else(|| ContractError::InsufficientCredits {
+                token_id: token_id.to_string(),
+            })?;
+        self.credits.save(storage, requester, &credits)?;
+        Ok(Some(price))
+    }
+
+    /// Matches native funds attached to a request against the project prices
+    fn native_payment(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+        funds: &[Coin],
+    ) -> Result<Option<Price>, ContractError> {
+        let prices = self.prices.may_load(storage, token_id)?.unwrap_or_default();
+        if prices.is_empty() {
+            if !funds.is_empty() {
+                return Err(PaymentError::NonPayable {}.into());
+            }
+            return Ok(None);
+        }
+
+        let coin = match funds {
+            [coin] => coin,
+            [] => return Err(PaymentError::NoFunds {}.into()),
+            _ => return Err(PaymentError::MultipleDenoms {}.into()),
+        };
+        self.check_payment(
+            storage,
+            token_id,
+            Denom::Native(coin.denom.clone()),
+            coin.amount,
+        )
+        .map(Some)
+    }
+
+    /// Matches native funds attached to a schedule against the project price of `max_runs`
+    /// requests, returning the price of one run, or none when the runs are paid as they trigger
+    fn schedule_payment(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+        funds: &[Coin],
+        max_runs: u32,
+    ) -> Result<Option<Price>, ContractError> {
+        let prices = self.prices.may_load(storage, token_id)?.unwrap_or_default();
+        if prices.is_empty() {
+            if !funds.is_empty() {
+                return Err(PaymentError::NonPayable {}.into());
+            }
+            return Ok(None);
+        }
+
+        let coin = match funds {
+            [coin] => coin,
+            // each run is paid when triggered
+            [] => return Ok(None),
+            _ => return Err(PaymentError::MultipleDenoms {}.into()),
+        };
+        let denom = Denom::Native(coin.denom.clone());
+        let price = prices
+            .into_iter()
+            .find(|price| price.denom == denom)
+            .ok_or_else(|| ContractError::UnacceptedDenom {
+                token_id: token_id.to_string(),
+                denom: coin.denom.clone(),
+            })?;
+
+        let expected = price.amount * Uint128::from(max_runs);
+        if coin.amount != expected {
+            return Err(ContractError::IncorrectPayment {
+                expected,
+                received: coin.amount,
+            });
+        }
+        Ok(Some(price))
+    }
+
+    /// Returns the project price in `denom`, failing if `amount` does not match it
+    fn check_payment(
+        &self,
+        storage: &dyn Storage,
+        token_id: &str,
+        denom: Denom,
+        amount: Uint128,
+    ) -> Result<Price, ContractError> {
+        let price = self
+            .prices
+            .may_load(storage, token_id)?
+            .unwrap_or_default()
+            .into_iter()
+            .find(|price| price.denom == denom)
+            .ok_or_else(|| ContractError::UnacceptedDenom {
+                token_id: token_id.to_string(),
+                denom: denom_label(&denom),
+            })?;
+
+        if price.amount != amount {
+            return Err(ContractError::IncorrectPayment {
+                expected: price.amount,
+                received: amount,
+            });
+        }
+        Ok(price)
+    }
+}
+
+/// Reports whether a submitted output finalized its task
+fn submission_response<C>(outcome: Option<Vec<CosmosMsg<C>>>) -> Response<C> {
+    match outcome {
+        Some(msgs) => Response::new()
+            .add_messages(msgs)
+            .add_attribute("status", "finalized"),
+        None => Response::new().add_attribute("status", "pending_quorum"),
+    }
+}
+
+fn assert_due(task: &Task, env: &Env) -> Result<(), ContractError> {
+    match task.not_before {
+        Some(not_before) if !not_before.is_expired(&env.block) => Err(ContractError::TaskNotDue {
+            task_id: task.tid.clone(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Blocks of the halts, latest first, that fall between `from` and `to`
+fn overlap(
+    halts: impl Iterator<Item = StdResult<(u64, u64)>>,
+    from: u64,
+    to: u64,
+) -> StdResult<u64> {
+    let mut blocks = 0;
+    for halt in halts {
+        let (start, end) = halt?;
+        if end <= from {
+            break;
+        }
+        blocks += end.min(to) - start.max(from);
+    }
+    Ok(blocks)
+}
+
+/// Checks one more request against a limit given the tasks it applies to
+fn check_rate_limit(
+    env: &Env,
+    token_id: &str,
+    limit: &RateLimit,
+    tasks: Vec<&Task>,
+) -> Result<(), ContractError> {
+    if let Some(max) = limit.max_pending {
+        let pending = tasks
+            .iter()
+            .filter(|task| task.output.is_none() && !task.cancelled)
+            .count();
+        if pending >= max as usize {
+            return Err(ContractError::TooManyPending {
+                token_id: token_id.to_string(),
+                max,
+            });
+        }
+    }
+    if let Some(max) = limit.max_requests {
+        let since = env.block.height.saturating_sub(limit.window);
+        let recent = tasks
+            .iter()
+            .filter(|task| task.requested_at.map_or(false, |height| height > since))
+            .count();
+        if recent >= max as usize {
+            return Err(ContractError::TooManyRequests {
+                token_id: token_id.to_string(),
+                max,
+                window: limit.window,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn find_task<'t>(
+    token: &'t TokenInfo<Extension>,
+    task_id: &str,
+) -> Result<&'t Task, ContractError> {
+    token
+        .extension
+        .as_ref()
+        .and_then(|metadata| metadata.tasks.as_ref())
+        .and_then(|tasks| tasks.iter().find(|task| task.tid == task_id))
+        .ok_or_else(|| ContractError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })
+}
+
+fn find_task_mut<'t>(
+    token: &'t mut TokenInfo<Extension>,
+    task_id: &str,
+) -> Result<&'t mut Task, ContractError> {
+    token
+        .extension
+        .as_mut()
+        .and_then(|metadata| metadata.tasks.as_mut())
+        .and_then(|tasks| tasks.iter_mut().find(|task| task.tid == task_id))
+        .ok_or_else(|| ContractError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })
+}
+
+fn sha256(data: &str) -> HexBinary {
+    HexBinary::from(Sha256::digest(data.as_bytes()).to_vec())
+}
+
+pub fn denom_label(denom: &Denom) -> String {
+    match denom {
+        Denom::Native(denom) => denom.clone(),
+        Denom::Cw20(addr) => addr.to_string(),
+    }
+}
+
+/// Builds the message paying `amount` of `denom` out of the contract
+pub fn transfer_msg<C: CustomMsg>(
+    recipient: &Addr,
+    denom: &Denom,
+    amount: Uint128,
+) -> StdResult<CosmosMsg<C>> {
+    Ok(match denom {
+        Denom::Native(denom) => BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins(amount.u128(), denom),
+        }
+        .into(),
+        Denom::Cw20(addr) => WasmMsg::Execute {
+            contract_addr: addr.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }
+        .into(),
+    })
 }
 
 impl<'a, T, C, E, Q> Gateway721Contract<'a, T, C, E, Q>
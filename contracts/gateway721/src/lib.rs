@@ -1,17 +1,20 @@
+pub mod error;
 mod execute;
 pub mod msg;
 mod query;
-mod state;
-mod traits;
+pub mod receiver;
+pub mod state;
+pub mod traits;
 
 use msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
 use state::Extension;
 use state::Gateway721Contract;
 
+use archway_bindings::{ArchwayMsg, ArchwayQuery};
 use cosmwasm_std::{
     entry_point, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdResult,
 };
-use cw721_base::ContractError;
+use error::ContractError;
 
 // Version info for migration
 const CONTRACT_NAME: &str = "Gateway721";
@@ -30,21 +33,21 @@ pub mod entry {
         env: Env,
         info: MessageInfo,
         msg: InstantiateMsg,
-    ) -> StdResult<Response> {
+    ) -> StdResult<Response<ArchwayMsg>> {
         cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-        let contract = Gateway721Contract::<Extension, Empty, Empty, Empty>::default();
+        let contract = Gateway721Contract::<Extension, ArchwayMsg, Empty, Empty>::default();
         contract.instantiate(deps, env, info, msg)
     }
 
     #[entry_point]
     pub fn execute(
-        deps: DepsMut,
+        deps: DepsMut<ArchwayQuery>,
         env: Env,
         info: MessageInfo,
         msg: ExecuteMsg<Extension, Empty>,
-    ) -> Result<Response, ContractError> {
-        let contract = Gateway721Contract::<Extension, Empty, Empty, Empty>::default();
+    ) -> Result<Response<ArchwayMsg>, ContractError> {
+        let contract = Gateway721Contract::<Extension, ArchwayMsg, Empty, Empty>::default();
 
         // project request queue
         let extension = if let ExecuteMsg::Mint { extension, .. } = &msg {
@@ -71,8 +74,8 @@ pub mod entry {
     }
 
     #[entry_point]
-    pub fn query(deps: Deps, env: Env, msg: QueryMsg<Empty>) -> StdResult<Binary> {
-        let contract = Gateway721Contract::<Extension, Empty, Empty, Empty>::default();
+    pub fn query(deps: Deps<ArchwayQuery>, env: Env, msg: QueryMsg<Empty>) -> StdResult<Binary> {
+        let contract = Gateway721Contract::<Extension, ArchwayMsg, Empty, Empty>::default();
         contract.query(deps, env, msg)
     }
 }
@@ -81,16 +84,105 @@ pub mod entry {
 mod tests {
     use super::*;
 
+    use archway_bindings::types::rewards::{
+        FlatFeeResponse, RewardsRecord, RewardsRecordsResponse,
+    };
     use cosmwasm_std::{
-        from_binary,
-        testing::{mock_dependencies, mock_env, mock_info},
+        coin, coins, from_json,
+        testing::{
+            mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MOCK_CONTRACT_ADDR,
+        },
+        to_json_binary, Addr, BankMsg, Binary, ContractResult, CosmosMsg, Decimal, HexBinary,
+        MemoryStorage, OwnedDeps, StdError, Uint128, WasmMsg,
+    };
+    use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Denom};
+    use cw721::{Expiration, NftInfoResponse, OwnerOfResponse};
+    use k256::ecdsa::signature::hazmat::PrehashSigner;
+    use k256::ecdsa::{Signature, SigningKey};
+    use msg::{
+        attestation_hash, commitment_hash, Attestation, BalancesResponse, BatchResult,
+        CheckRoyaltiesResponse, CommitmentsResponse, ExecuteMsg, ExecutorsResponse,
+        IncompleteProjectsResponse, InstantiateMsg, LicensesResponse, LineageResponse,
+        ListingsResponse, QueryMsg, ReceiveMsg, RequestItem, ResponseItem, RewardsResponse,
+        RoyaltiesInfoResponse, SchedulesResponse, StakeResponse, StatusResponse,
+        SubscriptionResponse, TaskIdsResponse,
+    };
+    use receiver::Gateway721ReceiveMsg;
+    use sha2::{Digest, Sha256};
+    use state::{
+        Balance, InputMapping, Interval, KeyScheme, LicenseOffer, Listing, Metadata, Pauses,
+        PipelineRun, PipelineStage, Plan, Price, RateLimit, Role, Suspension,
     };
-    use cw721::NftInfoResponse;
-    use msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-    use state::Metadata;
 
     const CREATOR: &str = "creator";
 
+    type MockDeps = OwnedDeps<MemoryStorage, MockApi, MockQuerier<ArchwayQuery>, ArchwayQuery>;
+
+    /// Mock dependencies whose contract has a flat fee and two rewards records waiting to be withdrawn
+    fn mock_deps() -> MockDeps {
+        archway_bindings::testing::mock_dependencies(|query| match query {
+            ArchwayQuery::FlatFee { .. } => to_json_binary(&FlatFeeResponse {
+                flat_fee_amount: coin(50, "aconst"),
+            })
+            .into(),
+            ArchwayQuery::RewardsRecords { .. } => to_json_binary(&RewardsRecordsResponse {
+                records: vec![
+                    RewardsRecord {
+                        id: 1,
+                        rewards_address: MOCK_CONTRACT_ADDR.to_string(),
+                        rewards: coins(1000, "aconst"),
+                        calculated_height: 1,
+                        calculated_time: "2023-08-01T00:00:00Z".to_string(),
+                    },
+                    RewardsRecord {
+                        id: 2,
+                        rewards_address: MOCK_CONTRACT_ADDR.to_string(),
+                        rewards: coins(501, "aconst"),
+                        calculated_height: 2,
+                        calculated_time: "2023-08-01T00:00:05Z".to_string(),
+                    },
+                ],
+                pagination: None,
+            })
+            .into(),
+            _ => ContractResult::Err("unsupported query".to_string()),
+        })
+    }
+
+    /// Instantiates the contract and mints token "0" owned by "john"
+    fn setup_project(
+        deps: &mut MockDeps,
+    ) -> Gateway721Contract<'static, Extension, ArchwayMsg, Empty, Empty> {
+        let contract = Gateway721Contract::<Extension, ArchwayMsg, Empty, Empty>::default();
+        let info = mock_info(CREATOR, &[]);
+        let init_msg = InstantiateMsg {
+            name: "SpaceShips".to_string(),
+            symbol: "SPACE".to_string(),
+        };
+        contract
+            .instantiate(
+                deps.as_mut().into_empty(),
+                mock_env(),
+                info.clone(),
+                init_msg,
+            )
+            .unwrap();
+
+        let exec_msg = ExecuteMsg::Mint {
+            token_id: "Not used".to_string(),
+            owner: "john".to_string(),
+            token_uri: None,
+            extension: Some(Metadata {
+                code: "MEOW".into(),
+                ..Metadata::default()
+            }),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), info, exec_msg)
+            .unwrap();
+        contract
+    }
+
     /// Make sure cw2 version info is properly initialized during instantiation,
     /// and NOT overwritten by the base contract.
     #[test]
@@ -114,9 +206,10 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_literal_unwrap)]
     fn use_metadata_extension() {
-        let mut deps = mock_dependencies();
-        let contract = Gateway721Contract::<Extension, Empty, Empty, Empty>::default();
+        let mut deps = mock_deps();
+        let contract = Gateway721Contract::<Extension, ArchwayMsg, Empty, Empty>::default();
 
         let info = mock_info(CREATOR, &[]);
         let init_msg = InstantiateMsg {
@@ -124,7 +217,12 @@ mod tests {
             symbol: "SPACE".to_string(),
         };
         contract
-            .instantiate(deps.as_mut(), mock_env(), info.clone(), init_msg)
+            .instantiate(
+                deps.as_mut().into_empty(),
+                mock_env(),
+                info.clone(),
+                init_msg,
+            )
             .unwrap();
 
         let extension = Some(Metadata {
@@ -149,7 +247,7 @@ mod tests {
         match res {
             Ok(binary_res) => {
                 let res: NftInfoResponse<Metadata> =
-                    from_binary(&binary_res).expect("Failed to parse binary response");
+                    from_json(&binary_res).expect("Failed to parse binary response");
                 // println!("{}", res.extension.code);
                 assert_eq!(
                     res.extension,
@@ -160,4 +258,2889 @@ mod tests {
             Err(err) => panic!("Query failed: {:?}", err),
         };
     }
+
+    #[test]
+    fn response_delivers_receive_msg() {
+        let mut deps = mock_deps();
+        let contract = Gateway721Contract::<Extension, ArchwayMsg, Empty, Empty>::default();
+
+        let info = mock_info(CREATOR, &[]);
+        let init_msg = InstantiateMsg {
+            name: "SpaceShips".to_string(),
+            symbol: "SPACE".to_string(),
+        };
+        contract
+            .instantiate(
+                deps.as_mut().into_empty(),
+                mock_env(),
+                info.clone(),
+                init_msg,
+            )
+            .unwrap();
+
+        let exec_msg = ExecuteMsg::Mint {
+            token_id: "Not used".to_string(),
+            owner: "john".to_string(),
+            token_uri: None,
+            extension: Some(Metadata {
+                code: "MEOW".into(),
+                destination: Some(Addr::unchecked("dest")),
+                ..Metadata::default()
+            }),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), info.clone(), exec_msg)
+            .unwrap();
+
+        let exec_msg = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{ \"a\": 5, \"b\": 3 }".to_string(),
+            not_before: None,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), exec_msg)
+            .unwrap();
+
+        let exec_msg = ExecuteMsg::Response {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+            output: "8".to_string(),
+            attestation: None,
+        };
+        let res = contract
+            .execute(deps.as_mut(), mock_env(), info, exec_msg)
+            .unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        let CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr, msg, ..
+        }) = &res.messages[0].msg
+        else {
+            panic!("unexpected message: {:?}", res.messages[0].msg);
+        };
+        assert_eq!(contract_addr, "dest");
+
+        let received = Gateway721ReceiveMsg::from_binary(msg).unwrap();
+        assert_eq!(received.token_id, "0");
+        assert_eq!(received.task_id, "0");
+        assert_eq!(received.requester, "alice");
+        assert_eq!(
+            received.input_hash,
+            receiver::input_hash("{ \"a\": 5, \"b\": 3 }")
+        );
+        assert_eq!(received.output, "8");
+
+        let gateways = vec![Addr::unchecked(MOCK_CONTRACT_ADDR)];
+        received
+            .verify(&Addr::unchecked(MOCK_CONTRACT_ADDR), &gateways)
+            .unwrap();
+        received
+            .verify(&Addr::unchecked("impostor"), &gateways)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn paid_requests_are_escrowed() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetPrices {
+            token_id: "0".to_string(),
+            prices: vec![
+                Price {
+                    denom: Denom::Native("uconst".into()),
+                    amount: Uint128::new(100),
+                },
+                Price {
+                    denom: Denom::Cw20(Addr::unchecked("cw20")),
+                    amount: Uint128::new(7),
+                },
+            ],
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(CREATOR, &[]),
+                exec_msg.clone(),
+            )
+            .unwrap_err();
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        // wrong native amount
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(50, "uconst")),
+                request.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            error::ContractError::IncorrectPayment {
+                expected: Uint128::new(100),
+                received: Uint128::new(50),
+            }
+        );
+
+        // native request, then cancel and refund
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(100, "uconst")),
+                request,
+            )
+            .unwrap();
+        let cancel = ExecuteMsg::CancelRequest {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+        };
+        let res = contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), cancel)
+            .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: coins(100, "uconst"),
+            })
+        );
+
+        // cw20 request, accrued to the token and withdrawn by its owner
+        let receive = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "bob".to_string(),
+            amount: Uint128::new(7),
+            msg: to_json_binary(&ReceiveMsg::Request {
+                token_id: "0".to_string(),
+                input: "{}".to_string(),
+                not_before: None,
+            })
+            .unwrap(),
+        });
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("cw20", &[]), receive)
+            .unwrap();
+        let response = ExecuteMsg::Response {
+            token_id: "0".to_string(),
+            task_id: "1".to_string(),
+            output: "{}".to_string(),
+            attestation: None,
+        };
+        let res = contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), response)
+            .unwrap();
+        assert!(res.messages.is_empty());
+
+        let res = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("john", &[]),
+                ExecuteMsg::Withdraw {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "cw20".to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "john".to_string(),
+                    amount: Uint128::new(7),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn revenue_share_follows_token_owner() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetRevenueShare {
+            owner: Decimal::percent(70),
+            executor: Decimal::percent(20),
+            treasury: Decimal::percent(10),
+            treasury_address: "treasury".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        let exec_msg = ExecuteMsg::SetPrices {
+            token_id: "0".to_string(),
+            prices: vec![Price {
+                denom: Denom::Native("uconst".into()),
+                amount: Uint128::new(100),
+            }],
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(100, "uconst")),
+                request,
+            )
+            .unwrap();
+        let response = ExecuteMsg::Response {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+            output: "{}".to_string(),
+            attestation: None,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), response)
+            .unwrap();
+
+        // the owner share moves with the token
+        let transfer = ExecuteMsg::TransferNft {
+            recipient: "mary".to_string(),
+            token_id: "0".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), transfer)
+            .unwrap();
+
+        let balance = |deps: &MockDeps, address: &str| -> Vec<Balance> {
+            let query_msg: QueryMsg<Empty> = QueryMsg::Balances {
+                address: address.to_string(),
+                start_after: None,
+                limit: None,
+            };
+            let res: BalancesResponse = from_json(
+                contract
+                    .query(deps.as_ref(), mock_env(), query_msg)
+                    .unwrap(),
+            )
+            .unwrap();
+            res.balances
+        };
+        let uconst = |amount: u128| {
+            vec![Balance {
+                denom: Denom::Native("uconst".into()),
+                amount: Uint128::new(amount),
+            }]
+        };
+        assert_eq!(balance(&deps, "john"), vec![]);
+        assert_eq!(balance(&deps, "mary"), uconst(70));
+        assert_eq!(balance(&deps, CREATOR), uconst(20));
+        assert_eq!(balance(&deps, "treasury"), uconst(10));
+
+        let res = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("mary", &[]),
+                ExecuteMsg::Withdraw {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "mary".to_string(),
+                amount: coins(70, "uconst"),
+            })
+        );
+        assert_eq!(balance(&deps, "mary"), vec![]);
+    }
+
+    #[test]
+    fn rewards_follow_completed_tasks() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetRevenueShare {
+            owner: Decimal::one(),
+            executor: Decimal::zero(),
+            treasury: Decimal::zero(),
+            treasury_address: "treasury".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+
+        for tid in ["0", "1"] {
+            let request = ExecuteMsg::Request {
+                token_id: "0".to_string(),
+                input: "{}".to_string(),
+                not_before: None,
+            };
+            contract
+                .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), request)
+                .unwrap();
+            let response = ExecuteMsg::Response {
+                token_id: "0".to_string(),
+                task_id: tid.to_string(),
+                output: "{}".to_string(),
+                attestation: None,
+            };
+            contract
+                .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), response)
+                .unwrap();
+        }
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Rewards {};
+        let res: RewardsResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.pending, coins(1501, "aconst"));
+        assert_eq!(res.total_points, 4);
+
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("john", &[]),
+                ExecuteMsg::DistributeRewards {},
+            )
+            .unwrap_err();
+        let res = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(CREATOR, &[]),
+                ExecuteMsg::DistributeRewards {},
+            )
+            .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Custom(ArchwayMsg::withdraw_rewards_by_ids(vec![1, 2]))
+        );
+
+        // executor and owner completed two tasks each, dust goes to the last of them
+        let balance = |deps: &MockDeps, address: &str| -> Vec<Balance> {
+            let query_msg: QueryMsg<Empty> = QueryMsg::Balances {
+                address: address.to_string(),
+                start_after: None,
+                limit: None,
+            };
+            let res: BalancesResponse = from_json(
+                contract
+                    .query(deps.as_ref(), mock_env(), query_msg)
+                    .unwrap(),
+            )
+            .unwrap();
+            res.balances
+        };
+        let aconst = |amount: u128| {
+            vec![Balance {
+                denom: Denom::Native("aconst".into()),
+                amount: Uint128::new(amount),
+            }]
+        };
+        assert_eq!(balance(&deps, CREATOR), aconst(750));
+        assert_eq!(balance(&deps, "john"), aconst(751));
+        assert_eq!(balance(&deps, "treasury"), vec![]);
+    }
+
+    #[test]
+    fn flat_fee_is_managed_through_bindings() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetFlatFee {
+            amount: coin(50, "aconst"),
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("john", &[]),
+                exec_msg.clone(),
+            )
+            .unwrap_err();
+        let res = contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Custom(ArchwayMsg::set_flat_fee(
+                MOCK_CONTRACT_ADDR,
+                coin(50, "aconst")
+            ))
+        );
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::FlatFee {};
+        let res: FlatFeeResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.flat_fee_amount, coin(50, "aconst"));
+    }
+
+    #[test]
+    fn quorum_finalizes_agreeing_output() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        for executor in ["exec1", "exec2", "exec3"] {
+            let exec_msg = ExecuteMsg::AddExecutor {
+                address: executor.to_string(),
+            };
+            contract
+                .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+                .unwrap();
+        }
+        let exec_msg = ExecuteMsg::SetQuorum {
+            token_id: "0".to_string(),
+            quorum: 2,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), request)
+            .unwrap();
+
+        let respond = |deps: &mut MockDeps, executor: &str, output: &str| {
+            let response = ExecuteMsg::Response {
+                token_id: "0".to_string(),
+                task_id: "0".to_string(),
+                output: output.to_string(),
+                attestation: None,
+            };
+            contract.execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(executor, &[]),
+                response,
+            )
+        };
+        respond(&mut deps, "mallory", "8").unwrap_err();
+        respond(&mut deps, "exec1", "8").unwrap();
+        respond(&mut deps, "exec1", "8").unwrap_err();
+        respond(&mut deps, "exec2", "9").unwrap();
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Remains {
+            token_id: "0".to_string(),
+        };
+        let res: TaskIdsResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.tids, vec!["0".to_string()]);
+
+        let res = respond(&mut deps, "exec3", "8").unwrap();
+        assert!(res
+            .attributes
+            .contains(&cosmwasm_std::Attribute::new("status", "finalized")));
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::NftInfo {
+            token_id: "0".to_string(),
+        };
+        let res: NftInfoResponse<Metadata> = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        let task = &res.extension.tasks.unwrap()[0];
+        assert_eq!(task.output, Some("8".to_string()));
+        assert_eq!(task.flagged, vec![Addr::unchecked("exec2")]);
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Executors {
+            start_after: None,
+            limit: None,
+        };
+        let res: ExecutorsResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        let ranking: Vec<_> = res
+            .executors
+            .iter()
+            .map(|executor| executor.address.as_str())
+            .collect();
+        assert_eq!(ranking, vec!["exec1", "exec3", "exec2"]);
+        assert_eq!(res.executors[0].stats.completed, 1);
+        assert_eq!(res.executors[0].average_latency, Some(0));
+        assert_eq!(res.executors[2].stats.disagreements, 1);
+        assert_eq!(res.executors[2].reliability, Decimal::zero());
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Executors {
+            start_after: Some("exec3".to_string()),
+            limit: Some(1),
+        };
+        let res: ExecutorsResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.executors[0].address, Addr::unchecked("exec2"));
+    }
+
+    #[test]
+    fn commit_reveal_hides_outputs_until_window_ends() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::AddExecutor {
+            address: "exec1".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        let exec_msg = ExecuteMsg::SetCommitWindow {
+            token_id: "0".to_string(),
+            blocks: 10,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), request)
+            .unwrap();
+
+        let response = ExecuteMsg::Response {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+            output: "8".to_string(),
+            attestation: None,
+        };
+        let err = contract
+            .execute(deps.as_mut(), mock_env(), mock_info("exec1", &[]), response)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CommitRevealRequired {
+                token_id: "0".to_string()
+            }
+        );
+
+        let commit = ExecuteMsg::CommitOutput {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+            hash: commitment_hash("exec1", "pep:per", "8"),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("exec1", &[]), commit)
+            .unwrap();
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Commitments {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+        };
+        let res: CommitmentsResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        let deadline = res.commit_deadline.unwrap();
+        assert_eq!(deadline, mock_env().block.height + 9);
+        assert_eq!(res.commitments.len(), 1);
+
+        let reveal = |deps: &mut MockDeps, height: u64, salt: &str, output: &str| {
+            let mut env = mock_env();
+            env.block.height = height;
+            let reveal = ExecuteMsg::RevealOutput {
+                token_id: "0".to_string(),
+                task_id: "0".to_string(),
+                output: output.to_string(),
+                salt: salt.to_string(),
+            };
+            contract.execute(deps.as_mut(), env, mock_info("exec1", &[]), reveal)
+        };
+        let err = reveal(&mut deps, deadline, "pep:per", "8").unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::RevealTooEarly {
+                task_id: "0".to_string(),
+                height: deadline + 1
+            }
+        );
+        let err = reveal(&mut deps, deadline + 1, "pep:per", "9").unwrap_err();
+        assert_eq!(err, ContractError::CommitmentMismatch {});
+        // the salt and output cannot be split differently
+        let err = reveal(&mut deps, deadline + 1, "pep", "per:8").unwrap_err();
+        assert_eq!(err, ContractError::CommitmentMismatch {});
+
+        let res = reveal(&mut deps, deadline + 1, "pep:per", "8").unwrap();
+        assert!(res
+            .attributes
+            .contains(&cosmwasm_std::Attribute::new("status", "finalized")));
+    }
+
+    #[test]
+    fn finalized_tasks_do_not_slash_unrevealed_commitments() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetStaking {
+            denom: "aconst".to_string(),
+            min_bond: Uint128::new(100),
+            unbonding_period: 60,
+            slash_ratio: Decimal::percent(10),
+            response_timeout: 5,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        for executor in ["exec1", "exec2"] {
+            contract
+                .execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info(executor, &coins(100, "aconst")),
+                    ExecuteMsg::RegisterExecutor {},
+                )
+                .unwrap();
+        }
+        let exec_msg = ExecuteMsg::SetCommitWindow {
+            token_id: "0".to_string(),
+            blocks: 10,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), request)
+            .unwrap();
+        for executor in ["exec1", "exec2"] {
+            let commit = ExecuteMsg::CommitOutput {
+                token_id: "0".to_string(),
+                task_id: "0".to_string(),
+                hash: commitment_hash(executor, "pepper", "8"),
+            };
+            contract
+                .execute(deps.as_mut(), mock_env(), mock_info(executor, &[]), commit)
+                .unwrap();
+        }
+
+        // exec1 finalizes the task, leaving exec2 nothing to reveal
+        let mut env = mock_env();
+        env.block.height += 10;
+        let reveal = ExecuteMsg::RevealOutput {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+            output: "8".to_string(),
+            salt: "pepper".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), env.clone(), mock_info("exec1", &[]), reveal)
+            .unwrap();
+
+        env.block.height += 10;
+        let slash = ExecuteMsg::SlashUnrevealed {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+            executor: "exec2".to_string(),
+        };
+        let err = contract
+            .execute(deps.as_mut(), env, mock_info("bob", &[]), slash)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CommitmentNotFound {
+                executor: "exec2".to_string(),
+                task_id: "0".to_string()
+            }
+        );
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Stake {
+            address: "exec2".to_string(),
+        };
+        let res: StakeResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.stake.bonded, Uint128::new(100));
+    }
+
+    #[test]
+    fn expired_claims_are_slashed() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetStaking {
+            denom: "aconst".to_string(),
+            min_bond: Uint128::new(100),
+            unbonding_period: 60,
+            slash_ratio: Decimal::percent(10),
+            response_timeout: 5,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+
+        let register = |deps: &mut MockDeps, executor: &str, amount: u128| {
+            contract.execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(executor, &coins(amount, "aconst")),
+                ExecuteMsg::RegisterExecutor {},
+            )
+        };
+        let err = register(&mut deps, "exec1", 50).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InsufficientBond {
+                min_bond: Uint128::new(100)
+            }
+        );
+        register(&mut deps, "exec1", 100).unwrap();
+        register(&mut deps, "exec2", 100).unwrap();
+
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), request)
+            .unwrap();
+        let claim = ExecuteMsg::ClaimTask {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("exec1", &[]), claim)
+            .unwrap();
+
+        let response = ExecuteMsg::Response {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+            output: "8".to_string(),
+            attestation: None,
+        };
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("exec2", &[]),
+                response.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TaskClaimed {
+                task_id: "0".to_string(),
+                executor: "exec1".to_string()
+            }
+        );
+
+        let slash = ExecuteMsg::SlashExpiredClaim {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("bob", &[]),
+                slash.clone(),
+            )
+            .unwrap_err();
+
+        // responses paused for two blocks stop slashing and push the claim expiry back
+        let set_pauses = |deps: &mut MockDeps, height: u64, responses: bool| {
+            let mut env = mock_env();
+            env.block.height += height;
+            let exec_msg = ExecuteMsg::SetPauses {
+                minting: None,
+                requests: None,
+                responses: Some(responses),
+                transfers: None,
+            };
+            contract
+                .execute(deps.as_mut(), env, mock_info(CREATOR, &[]), exec_msg)
+                .unwrap();
+        };
+        set_pauses(&mut deps, 2, true);
+        let mut env = mock_env();
+        env.block.height += 6;
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("bob", &[]),
+                slash.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Paused {
+                operation: "responses".to_string()
+            }
+        );
+        set_pauses(&mut deps, 4, false);
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("bob", &[]),
+                slash.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NotSlashable {
+                task_id: "0".to_string()
+            }
+        );
+        env.block.height += 2;
+        contract
+            .execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), slash)
+            .unwrap();
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Stake {
+            address: "exec1".to_string(),
+        };
+        let res: StakeResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.stake.bonded, Uint128::new(90));
+        assert_eq!(res.stake.slashed, Uint128::new(10));
+        assert!(!res.active);
+
+        // slashed stake goes to the treasury, the contract owner without a revenue share
+        let query_msg: QueryMsg<Empty> = QueryMsg::Balances {
+            address: CREATOR.to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let res: BalancesResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.balances[0].amount, Uint128::new(10));
+
+        // below the minimum bond, exec1 can no longer respond
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("exec1", &[]),
+                response.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InsufficientBond {
+                min_bond: Uint128::new(100)
+            }
+        );
+        contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("exec2", &[]),
+                response,
+            )
+            .unwrap();
+
+        let unbond = ExecuteMsg::Unbond {
+            amount: Uint128::new(90),
+        };
+        contract
+            .execute(deps.as_mut(), env.clone(), mock_info("exec1", &[]), unbond)
+            .unwrap();
+        contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("exec1", &[]),
+                ExecuteMsg::ClaimUnbonded {},
+            )
+            .unwrap_err();
+        env.block.time = env.block.time.plus_seconds(60);
+        let res = contract
+            .execute(
+                deps.as_mut(),
+                env,
+                mock_info("exec1", &[]),
+                ExecuteMsg::ClaimUnbonded {},
+            )
+            .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "exec1".to_string(),
+                amount: coins(90, "aconst"),
+            })
+        );
+    }
+
+    #[test]
+    fn relayed_attestations_credit_the_signer() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::AddExecutor {
+            address: "exec1".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        let key = SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+        let exec_msg = ExecuteMsg::SetExecutorKey {
+            scheme: KeyScheme::Secp256k1,
+            pubkey: Binary::from(key.verifying_key().to_encoded_point(true).as_bytes()),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("exec1", &[]), exec_msg)
+            .unwrap();
+
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), request)
+            .unwrap();
+
+        let hash = attestation_hash(
+            mock_env().contract.address.as_str(),
+            "0",
+            "0",
+            &HexBinary::from(Sha256::digest(b"MEOW").to_vec()),
+            &receiver::input_hash("{}"),
+            "8",
+        );
+        let signature: Signature = key.sign_prehash(hash.as_slice()).unwrap();
+        // fields are not joined ambiguously, a signature covers a single split of them
+        let code_hash = HexBinary::from(Sha256::digest(b"MEOW").to_vec());
+        assert_ne!(
+            attestation_hash("c", "0:1", "2", &code_hash, &code_hash, "8"),
+            attestation_hash("c", "0", "1:2", &code_hash, &code_hash, "8"),
+        );
+        let respond = |deps: &mut MockDeps, output: &str| {
+            let response = ExecuteMsg::Response {
+                token_id: "0".to_string(),
+                task_id: "0".to_string(),
+                output: output.to_string(),
+                attestation: Some(Attestation {
+                    executor: "exec1".to_string(),
+                    signature: Binary::from(signature.to_bytes().as_slice()),
+                }),
+            };
+            contract.execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("relayer", &[]),
+                response,
+            )
+        };
+
+        let err = respond(&mut deps, "9").unwrap_err();
+        assert_eq!(err, ContractError::InvalidAttestation {});
+        let res = respond(&mut deps, "8").unwrap();
+        assert!(res
+            .attributes
+            .contains(&cosmwasm_std::Attribute::new("executor", "exec1")));
+    }
+
+    #[test]
+    fn allowlist_replaces_executor_registry() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::AddExecutor {
+            address: "exec1".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        let exec_msg = ExecuteMsg::SetAllowlist {
+            token_id: "0".to_string(),
+            executors: vec!["john-node".to_string()],
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("exec1", &[]),
+                exec_msg.clone(),
+            )
+            .unwrap_err();
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), request)
+            .unwrap();
+
+        let response = ExecuteMsg::Response {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+            output: "8".to_string(),
+            attestation: None,
+        };
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("exec1", &[]),
+                response.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("john-node", &[]),
+                response,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn schedules_create_prepaid_tasks_when_due() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetPrices {
+            token_id: "0".to_string(),
+            prices: vec![Price {
+                denom: Denom::Native("uconst".to_string()),
+                amount: Uint128::new(100),
+            }],
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        let schedule = ExecuteMsg::ScheduleRequest {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            interval: Interval::Blocks(10),
+            max_runs: 3,
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(200, "uconst")),
+                schedule.clone(),
+            )
+            .unwrap_err();
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(300, "uconst")),
+                schedule,
+            )
+            .unwrap();
+
+        let trigger = |deps: &mut MockDeps, env: Env| {
+            contract.execute(
+                deps.as_mut(),
+                env,
+                mock_info("keeper", &[]),
+                ExecuteMsg::TriggerSchedule { id: 0 },
+            )
+        };
+        trigger(&mut deps, mock_env()).unwrap();
+        let err = trigger(&mut deps, mock_env()).unwrap_err();
+        assert_eq!(err, ContractError::ScheduleNotDue { id: 0 });
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Remains {
+            token_id: "0".to_string(),
+        };
+        let res: TaskIdsResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.tids, vec!["0".to_string()]);
+
+        let mut env = mock_env();
+        env.block.height += 10;
+        let query_msg: QueryMsg<Empty> = QueryMsg::DueSchedules {
+            start_after: None,
+            limit: None,
+        };
+        let res: SchedulesResponse = from_json(
+            contract
+                .query(deps.as_ref(), env.clone(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.schedules.len(), 1);
+        assert_eq!(res.schedules[0].schedule.runs, 1);
+
+        // schedules due at a height are listed before those due at a time
+        let exec_msg = ExecuteMsg::ScheduleRequest {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            interval: Interval::Seconds(60),
+            max_runs: 2,
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("alice", &coins(200, "uconst")),
+                exec_msg,
+            )
+            .unwrap();
+        contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("keeper", &[]),
+                ExecuteMsg::TriggerSchedule { id: 1 },
+            )
+            .unwrap();
+        let mut later = env.clone();
+        later.block.time = later.block.time.plus_seconds(60);
+        let due_ids = |deps: &MockDeps, env: Env, start_after: Option<u64>, limit: Option<u32>| {
+            let query_msg: QueryMsg<Empty> = QueryMsg::DueSchedules { start_after, limit };
+            let res: SchedulesResponse =
+                from_json(contract.query(deps.as_ref(), env, query_msg).unwrap()).unwrap();
+            res.schedules
+                .into_iter()
+                .map(|schedule| schedule.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(due_ids(&deps, env.clone(), None, None), vec![0]);
+        assert_eq!(due_ids(&deps, later.clone(), None, None), vec![0, 1]);
+        assert_eq!(due_ids(&deps, later.clone(), None, Some(1)), vec![0]);
+        assert_eq!(due_ids(&deps, later.clone(), Some(0), None), vec![1]);
+        assert_eq!(
+            due_ids(&deps, later.clone(), Some(1), None),
+            Vec::<u64>::new()
+        );
+
+        contract
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("alice", &[]),
+                ExecuteMsg::PauseSchedule { id: 0 },
+            )
+            .unwrap();
+        trigger(&mut deps, env.clone()).unwrap_err();
+        assert_eq!(due_ids(&deps, later, None, None), vec![1]);
+
+        // the two remaining runs are refunded
+        let res = contract
+            .execute(
+                deps.as_mut(),
+                env,
+                mock_info("alice", &[]),
+                ExecuteMsg::CancelSchedule { id: 0 },
+            )
+            .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: coins(200, "uconst"),
+            })
+        );
+    }
+
+    #[test]
+    fn deferred_requests_wait_for_not_before() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let not_before = mock_env().block.height + 5;
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: Some(Expiration::AtHeight(not_before)),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), request)
+            .unwrap();
+
+        let remains = |deps: &MockDeps, env: Env| -> Vec<String> {
+            let query_msg: QueryMsg<Empty> = QueryMsg::Remains {
+                token_id: "0".to_string(),
+            };
+            let res: TaskIdsResponse =
+                from_json(contract.query(deps.as_ref(), env, query_msg).unwrap()).unwrap();
+            res.tids
+        };
+        assert!(remains(&deps, mock_env()).is_empty());
+
+        let response = ExecuteMsg::Response {
+            token_id: "0".to_string(),
+            task_id: "0".to_string(),
+            output: "8".to_string(),
+            attestation: None,
+        };
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(CREATOR, &[]),
+                response.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TaskNotDue {
+                task_id: "0".to_string()
+            }
+        );
+
+        let mut env = mock_env();
+        env.block.height = not_before;
+        assert_eq!(remains(&deps, env.clone()), vec!["0".to_string()]);
+        contract
+            .execute(deps.as_mut(), env, mock_info(CREATOR, &[]), response)
+            .unwrap();
+    }
+
+    #[test]
+    fn batches_request_and_respond_atomically() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetPrices {
+            token_id: "0".to_string(),
+            prices: vec![Price {
+                denom: Denom::Native("uconst".into()),
+                amount: Uint128::new(100),
+            }],
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        let items = vec![
+            RequestItem {
+                token_id: "0".to_string(),
+                input: "{ \"a\": 1 }".to_string(),
+                not_before: None,
+            },
+            RequestItem {
+                token_id: "0".to_string(),
+                input: "{ \"a\": 2 }".to_string(),
+                not_before: None,
+            },
+        ];
+
+        // one price short, nothing is requested
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(150, "uconst")),
+                ExecuteMsg::BatchRequest {
+                    items: items.clone(),
+                },
+            )
+            .unwrap_err();
+
+        let res = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(200, "uconst")),
+                ExecuteMsg::BatchRequest { items },
+            )
+            .unwrap();
+        let result: BatchResult = from_json(res.data.unwrap()).unwrap();
+        let task_ids: Vec<String> = result.items.into_iter().map(|item| item.task_id).collect();
+        assert_eq!(task_ids, vec!["0".to_string(), "1".to_string()]);
+
+        let res = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(CREATOR, &[]),
+                ExecuteMsg::BatchResponse {
+                    items: task_ids
+                        .iter()
+                        .map(|task_id| ResponseItem {
+                            token_id: "0".to_string(),
+                            task_id: task_id.clone(),
+                            output: "3".to_string(),
+                            attestation: None,
+                        })
+                        .collect(),
+                },
+            )
+            .unwrap();
+        let result: BatchResult = from_json(res.data.unwrap()).unwrap();
+        assert!(result.items.iter().all(|item| item.status == "finalized"));
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Remains {
+            token_id: "0".to_string(),
+        };
+        let res: TaskIdsResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(res.tids.is_empty());
+    }
+
+    #[test]
+    fn pipelines_chain_outputs_into_next_stage() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::Mint {
+            token_id: "Not used".to_string(),
+            owner: "john".to_string(),
+            token_uri: None,
+            extension: Some(Metadata {
+                code: "PURR".into(),
+                ..Metadata::default()
+            }),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        let exec_msg = ExecuteMsg::SetPrices {
+            token_id: "1".to_string(),
+            prices: vec![Price {
+                denom: Denom::Native("uconst".into()),
+                amount: Uint128::new(100),
+            }],
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        let exec_msg = ExecuteMsg::CreatePipeline {
+            stages: vec![
+                PipelineStage {
+                    token_id: "0".to_string(),
+                    input: InputMapping::Output,
+                },
+                PipelineStage {
+                    token_id: "1".to_string(),
+                    input: InputMapping::Template("{ \"radius\": {{output}} }".to_string()),
+                },
+            ],
+            destination: Some("dest".to_string()),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), exec_msg)
+            .unwrap();
+        let exec_msg = ExecuteMsg::RunPipeline {
+            id: 0,
+            input: "{ \"a\": 5, \"b\": 3 }".to_string(),
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(100, "uconst")),
+                exec_msg,
+            )
+            .unwrap();
+
+        let respond = |deps: &mut MockDeps, token_id: &str, output: &str| {
+            let exec_msg = ExecuteMsg::Response {
+                token_id: token_id.to_string(),
+                task_id: "0".to_string(),
+                output: output.to_string(),
+                attestation: None,
+            };
+            contract
+                .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+                .unwrap()
+        };
+        let res = respond(&mut deps, "0", "8");
+        assert!(res.messages.is_empty());
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::NftInfo {
+            token_id: "1".to_string(),
+        };
+        let res: NftInfoResponse<Extension> = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        let tasks = res.extension.unwrap().tasks.unwrap();
+        assert_eq!(tasks[0].input, "{ \"radius\": 8 }");
+        assert_eq!(tasks[0].requester, Some(Addr::unchecked("alice")));
+
+        let res = respond(&mut deps, "1", "201.06");
+        let CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr, msg, ..
+        }) = &res.messages[0].msg
+        else {
+            panic!("unexpected message: {:?}", res.messages[0].msg);
+        };
+        assert_eq!(contract_addr, "dest");
+        let received = Gateway721ReceiveMsg::from_binary(msg).unwrap();
+        assert_eq!(received.token_id, "1");
+        assert_eq!(received.output, "201.06");
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::PipelineRun { id: 0 };
+        let run: Option<PipelineRun> = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(run.is_none());
+
+        // a suspended stage ends the run without blocking the stage before it
+        let exec_msg = ExecuteMsg::RunPipeline {
+            id: 0,
+            input: "{ \"a\": 1, \"b\": 2 }".to_string(),
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(100, "uconst")),
+                exec_msg,
+            )
+            .unwrap();
+        let exec_msg = ExecuteMsg::Suspend {
+            token_id: "1".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+        let exec_msg = ExecuteMsg::Response {
+            token_id: "0".to_string(),
+            task_id: "1".to_string(),
+            output: "3".to_string(),
+            attestation: None,
+        };
+        let res = contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: coins(100, "uconst"),
+            })
+        );
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::PipelineRun { id: 1 };
+        let run: Option<PipelineRun> = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        let run = run.unwrap();
+        assert_eq!(run.stage, 1);
+        assert_eq!(run.payments, vec![None]);
+        assert_eq!(run.failure, Some("Project 1 is suspended".to_string()));
+
+        // stages paid from credits or a subscription are refunded there
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(100, "uconst")),
+                ExecuteMsg::DepositCredits {},
+            )
+            .unwrap();
+        let exec_msg = ExecuteMsg::SetPlans {
+            token_id: "1".to_string(),
+            plans: vec![Plan {
+                price: coin(30, "uconst"),
+                period: 100,
+                max_requests: Some(1),
+            }],
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+        let run_pipeline = ExecuteMsg::RunPipeline {
+            id: 0,
+            input: "{ \"a\": 1, \"b\": 2 }".to_string(),
+        };
+        let unsuspend = ExecuteMsg::Unsuspend {
+            token_id: "1".to_string(),
+        };
+        let subscribe = ExecuteMsg::Subscribe {
+            token_id: "1".to_string(),
+            plan: 0,
+        };
+        let suspend = ExecuteMsg::Suspend {
+            token_id: "1".to_string(),
+        };
+        for (task_id, subscription) in [("2", None), ("3", Some(subscribe))] {
+            contract
+                .execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info("john", &[]),
+                    unsuspend.clone(),
+                )
+                .unwrap();
+            if let Some(subscribe) = subscription {
+                contract
+                    .execute(
+                        deps.as_mut(),
+                        mock_env(),
+                        mock_info("alice", &coins(30, "uconst")),
+                        subscribe,
+                    )
+                    .unwrap();
+            }
+            contract
+                .execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info("alice", &[]),
+                    run_pipeline.clone(),
+                )
+                .unwrap();
+            contract
+                .execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info("john", &[]),
+                    suspend.clone(),
+                )
+                .unwrap();
+            let exec_msg = ExecuteMsg::Response {
+                token_id: "0".to_string(),
+                task_id: task_id.to_string(),
+                output: "3".to_string(),
+                attestation: None,
+            };
+            let res = contract
+                .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+                .unwrap();
+            assert!(res.messages.is_empty());
+
+            let query_msg: QueryMsg<Empty> = QueryMsg::Credits {
+                address: "alice".to_string(),
+            };
+            let res: BalancesResponse = from_json(
+                contract
+                    .query(deps.as_ref(), mock_env(), query_msg)
+                    .unwrap(),
+            )
+            .unwrap();
+            assert_eq!(res.balances[0].amount, Uint128::new(100));
+        }
+        let query_msg: QueryMsg<Empty> = QueryMsg::Subscription {
+            token_id: "1".to_string(),
+            subscriber: "alice".to_string(),
+        };
+        let res: SubscriptionResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.remaining, Some(1));
+    }
+
+    #[test]
+    fn composites_aggregate_child_outputs() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        for extension in [
+            Metadata {
+                code: "PURR".into(),
+                ..Metadata::default()
+            },
+            Metadata {
+                children: Some(vec!["0".to_string(), "1".to_string()]),
+                ..Metadata::default()
+            },
+        ] {
+            let exec_msg = ExecuteMsg::Mint {
+                token_id: "Not used".to_string(),
+                owner: "john".to_string(),
+                token_uri: None,
+                extension: Some(extension),
+            };
+            contract
+                .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+                .unwrap();
+        }
+        let exec_msg = ExecuteMsg::SetRevenueShare {
+            owner: Decimal::percent(70),
+            executor: Decimal::percent(20),
+            treasury: Decimal::percent(10),
+            treasury_address: "treasury".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        for executor in ["exec1", "exec2"] {
+            let exec_msg = ExecuteMsg::AddExecutor {
+                address: executor.to_string(),
+            };
+            contract
+                .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+                .unwrap();
+        }
+        let exec_msg = ExecuteMsg::SetPrices {
+            token_id: "2".to_string(),
+            prices: vec![Price {
+                denom: Denom::Native("uconst".into()),
+                amount: Uint128::new(100),
+            }],
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        let exec_msg = ExecuteMsg::Request {
+            token_id: "2".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(100, "uconst")),
+                exec_msg,
+            )
+            .unwrap();
+
+        let response = |token_id: &str, output: &str| ExecuteMsg::Response {
+            token_id: token_id.to_string(),
+            task_id: "0".to_string(),
+            output: output.to_string(),
+            attestation: None,
+        };
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(CREATOR, &[]),
+                response("2", "[]"),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CompositeTask {
+                task_id: "0".to_string()
+            }
+        );
+
+        for (executor, token_id, output) in [("exec1", "1", "b"), ("exec2", "0", "a")] {
+            contract
+                .execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info(executor, &[]),
+                    response(token_id, output),
+                )
+                .unwrap();
+        }
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::NftInfo {
+            token_id: "2".to_string(),
+        };
+        let res: NftInfoResponse<Extension> = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        let tasks = res.extension.unwrap().tasks.unwrap();
+        assert_eq!(tasks[0].output, Some("[\"a\",\"b\"]".to_string()));
+
+        // the executors of the children share the executor cut of the composite
+        for (address, amount) in [("exec1", 10), ("exec2", 10), ("treasury", 10), ("john", 70)] {
+            let query_msg: QueryMsg<Empty> = QueryMsg::Balances {
+                address: address.to_string(),
+                start_after: None,
+                limit: None,
+            };
+            let res: BalancesResponse = from_json(
+                contract
+                    .query(deps.as_ref(), mock_env(), query_msg)
+                    .unwrap(),
+            )
+            .unwrap();
+            assert_eq!(res.balances[0].amount, Uint128::new(amount));
+        }
+    }
+
+    #[test]
+    fn forks_pass_fees_up_the_lineage() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetLineageShare {
+            share: Decimal::percent(50),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+
+        for (sender, parent) in [("alice", "0"), ("bob", "1")] {
+            let exec_msg = ExecuteMsg::Fork {
+                token_id: parent.to_string(),
+                code: Some(format!("{sender} was here")),
+            };
+            contract
+                .execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), exec_msg)
+                .unwrap();
+        }
+
+        let lineage = |deps: &MockDeps, query_msg: QueryMsg<Empty>| -> Vec<String> {
+            let res: LineageResponse = from_json(
+                contract
+                    .query(deps.as_ref(), mock_env(), query_msg)
+                    .unwrap(),
+            )
+            .unwrap();
+            res.token_ids
+        };
+        let ancestors = lineage(
+            &deps,
+            QueryMsg::Ancestors {
+                token_id: "2".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        );
+        assert_eq!(ancestors, vec!["1".to_string(), "0".to_string()]);
+        let ancestors = lineage(
+            &deps,
+            QueryMsg::Ancestors {
+                token_id: "2".to_string(),
+                start_after: Some("1".to_string()),
+                limit: Some(1),
+            },
+        );
+        assert_eq!(ancestors, vec!["0".to_string()]);
+
+        // forks of an undescribed project wait for a describer
+        let res: IncompleteProjectsResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), QueryMsg::IncompleteProjects {})
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.pids, vec!["1".to_string(), "2".to_string()]);
+        let descendants = lineage(
+            &deps,
+            QueryMsg::Descendants {
+                token_id: "0".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        );
+        assert_eq!(descendants, vec!["1".to_string(), "2".to_string()]);
+        let descendants = lineage(
+            &deps,
+            QueryMsg::Descendants {
+                token_id: "0".to_string(),
+                start_after: Some("1".to_string()),
+                limit: Some(1),
+            },
+        );
+        assert_eq!(descendants, vec!["2".to_string()]);
+
+        let exec_msg = ExecuteMsg::SetPrices {
+            token_id: "2".to_string(),
+            prices: vec![Price {
+                denom: Denom::Native("uconst".into()),
+                amount: Uint128::new(100),
+            }],
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), exec_msg)
+            .unwrap();
+        let request = ExecuteMsg::Request {
+            token_id: "2".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        let respond = |deps: &mut MockDeps, task_id: &str| {
+            let response = ExecuteMsg::Response {
+                token_id: "2".to_string(),
+                task_id: task_id.to_string(),
+                output: "8".to_string(),
+                attestation: None,
+            };
+            contract
+                .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), response)
+                .unwrap();
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("carol", &coins(100, "uconst")),
+                request.clone(),
+            )
+            .unwrap();
+        respond(&mut deps, "0");
+        let query_msg: QueryMsg<Empty> = QueryMsg::Balances {
+            address: "alice".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let res: BalancesResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.balances[0].amount, Uint128::new(25));
+
+        // a burned ancestor ends the lineage, its fork keeps what it would have passed on
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                ExecuteMsg::Burn {
+                    token_id: "1".to_string(),
+                },
+            )
+            .unwrap();
+        let ancestors = lineage(
+            &deps,
+            QueryMsg::Ancestors {
+                token_id: "2".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        );
+        assert!(ancestors.is_empty());
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("carol", &coins(100, "uconst")),
+                request,
+            )
+            .unwrap();
+        respond(&mut deps, "1");
+
+        for (address, amount) in [("bob", 150), ("john", 25)] {
+            let query_msg: QueryMsg<Empty> = QueryMsg::Balances {
+                address: address.to_string(),
+                start_after: None,
+                limit: None,
+            };
+            let res: BalancesResponse = from_json(
+                contract
+                    .query(deps.as_ref(), mock_env(), query_msg)
+                    .unwrap(),
+            )
+            .unwrap();
+            assert_eq!(res.balances[0].amount, Uint128::new(amount));
+        }
+    }
+
+    #[test]
+    fn royalties_are_capped_and_paid_to_the_minted_owner() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetMaxRoyaltyPercentage { percentage: 10 };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+
+        let mint = |percentage: u64| ExecuteMsg::Mint {
+            token_id: "Not used".to_string(),
+            owner: "john".to_string(),
+            token_uri: None,
+            extension: Some(Metadata {
+                code: "PURR".into(),
+                royalty_percentage: Some(percentage),
+                ..Metadata::default()
+            }),
+        };
+        let err = contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), mint(15))
+            .unwrap_err();
+        assert_eq!(err, ContractError::RoyaltyTooHigh { max: 10 });
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), mint(5))
+            .unwrap();
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::RoyaltyInfo {
+            token_id: "1".to_string(),
+            sale_price: Uint128::new(1000),
+        };
+        let res: RoyaltiesInfoResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.address, "john");
+        assert_eq!(res.royalty_amount, Uint128::new(50));
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::CheckRoyalties {};
+        let res: CheckRoyaltiesResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(res.royalty_payments);
+    }
+
+    #[test]
+    fn marketplace_escrows_and_settles_sales() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::Mint {
+            token_id: "Not used".to_string(),
+            owner: "john".to_string(),
+            token_uri: None,
+            extension: Some(Metadata {
+                code: "PURR".into(),
+                royalty_percentage: Some(10),
+                ..Metadata::default()
+            }),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        let exec_msg = ExecuteMsg::SetMarketFee {
+            fee: Decimal::percent(5),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+
+        let exec_msg = ExecuteMsg::ListNft {
+            token_id: "1".to_string(),
+            price: coin(1000, "uconst"),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        // held by the contract while listed
+        let exec_msg = ExecuteMsg::TransferNft {
+            recipient: "bob".to_string(),
+            token_id: "1".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap_err();
+        let owner_of = |deps: &MockDeps| {
+            let query_msg: QueryMsg<Empty> = QueryMsg::OwnerOf {
+                token_id: "1".to_string(),
+                include_expired: None,
+            };
+            let res: OwnerOfResponse = from_json(
+                contract
+                    .query(deps.as_ref(), mock_env(), query_msg)
+                    .unwrap(),
+            )
+            .unwrap();
+            res.owner
+        };
+        assert_eq!(owner_of(&deps), MOCK_CONTRACT_ADDR);
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::ListingsByPrice {
+            denom: "uconst".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let res: ListingsResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.listings.len(), 1);
+        assert_eq!(res.listings[0].listing.seller, Addr::unchecked("john"));
+
+        let buy = ExecuteMsg::BuyNft {
+            token_id: "1".to_string(),
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("john", &coins(1000, "uconst")),
+                buy,
+            )
+            .unwrap_err();
+
+        let res = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(1000, "uconst")),
+                ExecuteMsg::BuyNft {
+                    token_id: "1".to_string(),
+                },
+            )
+            .unwrap();
+        let payouts: Vec<CosmosMsg<ArchwayMsg>> =
+            res.messages.into_iter().map(|msg| msg.msg).collect();
+        assert_eq!(
+            payouts,
+            vec![
+                BankMsg::Send {
+                    to_address: "john".to_string(),
+                    amount: coins(100, "uconst"),
+                }
+                .into(),
+                BankMsg::Send {
+                    to_address: "john".to_string(),
+                    amount: coins(850, "uconst"),
+                }
+                .into(),
+            ]
+        );
+
+        assert_eq!(owner_of(&deps), "alice");
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Balances {
+            address: CREATOR.to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let res: BalancesResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.balances[0].amount, Uint128::new(50));
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Listing {
+            token_id: "1".to_string(),
+        };
+        let res: Option<Listing> = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(res.is_none());
+
+        // delisting gives the token back to its seller
+        let exec_msg = ExecuteMsg::ListNft {
+            token_id: "1".to_string(),
+            price: coin(1000, "uconst"),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), exec_msg)
+            .unwrap();
+        let exec_msg = ExecuteMsg::DelistNft {
+            token_id: "1".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), exec_msg)
+            .unwrap();
+        assert_eq!(owner_of(&deps), "alice");
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("carol", &coins(1000, "uconst")),
+                ExecuteMsg::BuyNft {
+                    token_id: "1".to_string(),
+                },
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NotListed {
+                token_id: "1".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn private_projects_need_a_license() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetPrivate {
+            token_id: "0".to_string(),
+            private: true,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        let no_license = ContractError::NoLicense {
+            token_id: "0".to_string(),
+        };
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                request.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(err, no_license);
+
+        // one request only
+        let exec_msg = ExecuteMsg::IssueLicense {
+            token_id: "0".to_string(),
+            holder: "alice".to_string(),
+            expires: None,
+            uses: Some(1),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                request.clone(),
+            )
+            .unwrap();
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                request.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(err, no_license);
+
+        let exec_msg = ExecuteMsg::SetLicenseOffer {
+            token_id: "0".to_string(),
+            offer: Some(LicenseOffer {
+                price: coin(10, "uconst"),
+                duration: Some(Interval::Blocks(100)),
+                uses: None,
+            }),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+        let buy_license = ExecuteMsg::BuyLicense {
+            token_id: "0".to_string(),
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("bob", &coins(10, "uconst")),
+                buy_license.clone(),
+            )
+            .unwrap();
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("bob", &[]),
+                request.clone(),
+            )
+            .unwrap();
+        // the license is not bought again while it is valid
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("bob", &coins(10, "uconst")),
+                buy_license.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::LicenseActive {
+                token_id: "0".to_string()
+            }
+        );
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("bob", &coins(10, "uconst")),
+                ExecuteMsg::BuyLicense {
+                    token_id: "9".to_string(),
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::NotFound { .. })));
+
+        let mut env = mock_env();
+        env.block.height += 100;
+        contract
+            .execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), request)
+            .unwrap_err();
+        contract
+            .execute(
+                deps.as_mut(),
+                env,
+                mock_info("bob", &coins(10, "uconst")),
+                buy_license,
+            )
+            .unwrap();
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Licenses {
+            token_id: "0".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let res: LicensesResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.licenses.len(), 2);
+        let query_msg: QueryMsg<Empty> = QueryMsg::HolderLicenses {
+            holder: "bob".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let res: LicensesResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.licenses[0].token_id, "0");
+    }
+
+    #[test]
+    fn requests_without_funds_use_credits() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetPrices {
+            token_id: "0".to_string(),
+            prices: vec![Price {
+                denom: Denom::Native("uconst".into()),
+                amount: Uint128::new(100),
+            }],
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(250, "uconst")),
+                ExecuteMsg::DepositCredits {},
+            )
+            .unwrap();
+
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                request.clone(),
+            )
+            .unwrap();
+        // batches and schedules sent without funds are paid from credits too
+        let exec_msg = ExecuteMsg::BatchRequest {
+            items: vec![RequestItem {
+                token_id: "0".to_string(),
+                input: "{}".to_string(),
+                not_before: None,
+            }],
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), exec_msg)
+            .unwrap();
+        let exec_msg = ExecuteMsg::ScheduleRequest {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            interval: Interval::Blocks(10),
+            max_runs: 1,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), exec_msg)
+            .unwrap();
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("bob", &[]),
+                ExecuteMsg::TriggerSchedule { id: 0 },
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InsufficientCredits {
+                token_id: "0".to_string()
+            }
+        );
+        let err = contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), request)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InsufficientCredits {
+                token_id: "0".to_string()
+            }
+        );
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Credits {
+            address: "alice".to_string(),
+        };
+        let res: BalancesResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.balances[0].amount, Uint128::new(50));
+
+        let res = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                ExecuteMsg::WithdrawCredits { amount: None },
+            )
+            .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: coins(50, "uconst"),
+            })
+        );
+    }
+
+    #[test]
+    fn subscriptions_enforce_quota_per_period() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetPrices {
+            token_id: "0".to_string(),
+            prices: vec![Price {
+                denom: Denom::Native("uconst".into()),
+                amount: Uint128::new(100),
+            }],
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+        let exec_msg = ExecuteMsg::SetPlans {
+            token_id: "0".to_string(),
+            plans: vec![
+                Plan {
+                    price: coin(30, "uconst"),
+                    period: 100,
+                    max_requests: Some(1),
+                },
+                Plan {
+                    price: coin(50, "uconst"),
+                    period: 100,
+                    max_requests: None,
+                },
+            ],
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        // two periods
+        for _ in 0..2 {
+            contract
+                .execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info("alice", &coins(30, "uconst")),
+                    ExecuteMsg::Subscribe {
+                        token_id: "0".to_string(),
+                        plan: 0,
+                    },
+                )
+                .unwrap();
+        }
+        // the active plan cannot be swapped for another
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &coins(50, "uconst")),
+                ExecuteMsg::Subscribe {
+                    token_id: "0".to_string(),
+                    plan: 1,
+                },
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::PlanMismatch {
+                token_id: "0".to_string(),
+                plan: 1
+            }
+        );
+
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                request.clone(),
+            )
+            .unwrap();
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                request.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::QuotaExceeded {
+                token_id: "0".to_string()
+            }
+        );
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let query_msg: QueryMsg<Empty> = QueryMsg::Subscription {
+            token_id: "0".to_string(),
+            subscriber: "alice".to_string(),
+        };
+        let res: SubscriptionResponse = from_json(
+            contract
+                .query(deps.as_ref(), env.clone(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(res.active);
+        assert_eq!(res.remaining, Some(1));
+        contract
+            .execute(deps.as_mut(), env, mock_info("alice", &[]), request)
+            .unwrap();
+    }
+
+    #[test]
+    fn rate_limits_cap_requesters() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetRateLimits {
+            token_id: "0".to_string(),
+            per_address: Some(RateLimit {
+                max_pending: Some(1),
+                max_requests: None,
+                window: 0,
+            }),
+            per_project: Some(RateLimit {
+                max_pending: None,
+                max_requests: Some(2),
+                window: 10,
+            }),
+            exempt: vec!["carol".to_string()],
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                request.clone(),
+            )
+            .unwrap();
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                request.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TooManyPending {
+                token_id: "0".to_string(),
+                max: 1
+            }
+        );
+
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("bob", &[]),
+                request.clone(),
+            )
+            .unwrap();
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("dave", &[]),
+                request.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TooManyRequests {
+                token_id: "0".to_string(),
+                max: 2,
+                window: 10
+            }
+        );
+
+        // exempt addresses and later windows pass
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("carol", &[]),
+                request.clone(),
+            )
+            .unwrap();
+        let mut env = mock_env();
+        env.block.height += 10;
+        contract
+            .execute(deps.as_mut(), env, mock_info("dave", &[]), request)
+            .unwrap();
+    }
+
+    #[test]
+    fn pauses_and_suspensions_stop_operations() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let exec_msg = ExecuteMsg::SetPauses {
+            minting: None,
+            requests: Some(true),
+            responses: None,
+            transfers: None,
+        };
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("john", &[]),
+                exec_msg.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(err, ContractError::MissingRole { role: Role::Admin });
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+
+        let request = ExecuteMsg::Request {
+            token_id: "0".to_string(),
+            input: "{}".to_string(),
+            not_before: None,
+        };
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                request.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Paused {
+                operation: "requests".to_string()
+            }
+        );
+
+        // a moderator suspends the project while requests are unpaused
+        let exec_msg = ExecuteMsg::SetPauses {
+            minting: None,
+            requests: Some(false),
+            responses: None,
+            transfers: None,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        let exec_msg = ExecuteMsg::GrantRole {
+            address: "mod".to_string(),
+            role: Role::Moderator,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        let suspend = ExecuteMsg::Suspend {
+            token_id: "0".to_string(),
+        };
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                suspend.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("mod", &[]),
+                suspend.clone(),
+            )
+            .unwrap();
+
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                request.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Suspended {
+                token_id: "0".to_string()
+            }
+        );
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Status {
+            token_id: Some("0".to_string()),
+        };
+        let res: StatusResponse = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            res,
+            StatusResponse {
+                pauses: Pauses::default(),
+                suspension: Some(Suspension {
+                    suspended_by: Addr::unchecked("mod"),
+                    moderated: true,
+                }),
+            }
+        );
+
+        // only a moderator lifts a suspension the owner did not set
+        let unsuspend = ExecuteMsg::Unsuspend {
+            token_id: "0".to_string(),
+        };
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("john", &[]),
+                unsuspend.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("mod", &[]),
+                unsuspend.clone(),
+            )
+            .unwrap();
+
+        // an owner's suspension passes on with the token for the next owner to lift
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), suspend)
+            .unwrap();
+        let exec_msg = ExecuteMsg::TransferNft {
+            recipient: "bob".to_string(),
+            token_id: "0".to_string(),
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("john", &[]), exec_msg)
+            .unwrap();
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("john", &[]),
+                unsuspend.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), unsuspend)
+            .unwrap();
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), request)
+            .unwrap();
+    }
+
+    #[test]
+    fn roles_gate_privileged_operations() {
+        let mut deps = mock_deps();
+        let contract = setup_project(&mut deps);
+
+        let update = ExecuteMsg::Update {
+            token_id: "0".to_string(),
+            title: "Meow".to_string(),
+            description: "Says meow".to_string(),
+        };
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                update.clone(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::MissingRole {
+                role: Role::Describer
+            }
+        );
+
+        // an admin appointed by the owner grants the describer role
+        let exec_msg = ExecuteMsg::GrantRole {
+            address: "admin".to_string(),
+            role: Role::Admin,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), exec_msg)
+            .unwrap();
+        let exec_msg = ExecuteMsg::GrantRole {
+            address: "alice".to_string(),
+            role: Role::Describer,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), exec_msg)
+            .unwrap();
+
+        let query_msg: QueryMsg<Empty> = QueryMsg::Roles {
+            address: "alice".to_string(),
+        };
+        let res: Vec<Role> = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res, vec![Role::Describer]);
+
+        // describers cannot manage executors
+        let exec_msg = ExecuteMsg::AddExecutor {
+            address: "bob".to_string(),
+        };
+        let err = contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), exec_msg)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::MissingRole {
+                role: Role::ExecutorManager
+            }
+        );
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), update)
+            .unwrap();
+
+        let exec_msg = ExecuteMsg::RevokeRole {
+            address: "alice".to_string(),
+            role: Role::Describer,
+        };
+        contract
+            .execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), exec_msg)
+            .unwrap();
+        let query_msg: QueryMsg<Empty> = QueryMsg::Roles {
+            address: "alice".to_string(),
+        };
+        let res: Vec<Role> = from_json(
+            contract
+                .query(deps.as_ref(), mock_env(), query_msg)
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(res.is_empty());
+    }
 }
@@ -1,9 +1,17 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Binary;
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, HexBinary, Uint128};
+use cw20::Cw20ReceiveMsg;
 use cw721::Expiration;
 use cw721_base::MinterResponse;
 use cw_ownable::{cw_ownable_execute, cw_ownable_query};
 use schemars::JsonSchema;
+use sha2::{Digest, Sha256};
+
+use crate::state::{
+    Balance, ExecutorKey, ExecutorStats, Interval, KeyScheme, License, LicenseOffer, Listing,
+    Pauses, Pipeline, PipelineRun, PipelineStage, Plan, Price, RateLimit, RateLimits, RevenueShare,
+    Role, Schedule, Stake, StakingConfig, Submission, Subscription, Suspension, TaskClaim,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -23,6 +31,195 @@ pub struct TaskIdsResponse {
     pub tids: Vec<String>,
 }
 
+#[cw_serde]
+pub struct PricesResponse {
+    pub prices: Vec<Price>,
+}
+
+#[cw_serde]
+pub struct BalancesResponse {
+    pub balances: Vec<Balance>,
+}
+
+#[cw_serde]
+pub struct RewardsResponse {
+    /// Archway rewards credited to the contract and not yet withdrawn
+    pub pending: Vec<Coin>,
+    /// Completed tasks counted since the last distribution
+    pub total_points: u64,
+}
+
+#[cw_serde]
+pub struct QuorumResponse {
+    pub quorum: u32,
+}
+
+#[cw_serde]
+pub struct AllowlistResponse {
+    /// Empty when any registered executor can work on the project
+    pub executors: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct ScheduleResponse {
+    pub id: u64,
+    pub schedule: Schedule,
+}
+
+#[cw_serde]
+pub struct SchedulesResponse {
+    pub schedules: Vec<ScheduleResponse>,
+}
+
+/// Same as the cw2981 `royalty_info` response
+#[cw_serde]
+pub struct RoyaltiesInfoResponse {
+    pub address: String,
+    pub royalty_amount: Uint128,
+}
+
+/// Same as the cw2981 `check_royalties` response
+#[cw_serde]
+pub struct CheckRoyaltiesResponse {
+    pub royalty_payments: bool,
+}
+
+#[cw_serde]
+pub struct ListingResponse {
+    pub token_id: String,
+    pub listing: Listing,
+}
+
+#[cw_serde]
+pub struct ListingsResponse {
+    pub listings: Vec<ListingResponse>,
+}
+
+/// Position to continue listing by price from
+#[cw_serde]
+pub struct ListingCursor {
+    pub amount: Uint128,
+    pub token_id: String,
+}
+
+#[cw_serde]
+pub struct LicenseResponse {
+    pub token_id: String,
+    pub license: License,
+}
+
+#[cw_serde]
+pub struct LicensesResponse {
+    pub licenses: Vec<LicenseResponse>,
+}
+
+#[cw_serde]
+pub struct PlansResponse {
+    pub plans: Vec<Plan>,
+}
+
+#[cw_serde]
+pub struct StatusResponse {
+    pub pauses: Pauses,
+    /// Who suspended the given project, none if it is not suspended or none was given
+    pub suspension: Option<Suspension>,
+}
+
+#[cw_serde]
+pub struct SubscriptionResponse {
+    pub subscription: Option<Subscription>,
+    pub active: bool,
+    /// Requests left in the current period, unlimited when unset
+    pub remaining: Option<u32>,
+}
+
+#[cw_serde]
+pub struct LineageResponse {
+    pub token_ids: Vec<String>,
+}
+
+#[cw_serde]
+pub struct PipelineResponse {
+    pub id: u64,
+    pub pipeline: Pipeline,
+}
+
+#[cw_serde]
+pub struct SubmissionsResponse {
+    pub submissions: Vec<Submission>,
+}
+
+#[cw_serde]
+pub struct Commitment {
+    pub executor: Addr,
+    pub hash: HexBinary,
+}
+
+#[cw_serde]
+pub struct CommitmentsResponse {
+    /// Last block height accepting commitments, null without a commit window
+    pub commit_deadline: Option<u64>,
+    pub commitments: Vec<Commitment>,
+}
+
+#[cw_serde]
+pub struct StakeResponse {
+    pub stake: Stake,
+    /// Whether the bond is enough to respond
+    pub active: bool,
+}
+
+#[cw_serde]
+pub struct ExecutorStatsResponse {
+    pub address: Addr,
+    pub stats: ExecutorStats,
+    /// Average blocks from request to finalization, null before any completed task
+    pub average_latency: Option<u64>,
+    /// Share of completed tasks among all tasks the executor was accountable for
+    pub reliability: Decimal,
+}
+
+#[cw_serde]
+pub struct ExecutorsResponse {
+    pub executors: Vec<ExecutorStatsResponse>,
+}
+
+#[cw_serde]
+pub struct RequestItem {
+    pub token_id: String,
+    pub input: String,
+    pub not_before: Option<Expiration>,
+}
+
+#[cw_serde]
+pub struct ResponseItem {
+    pub token_id: String,
+    pub task_id: String,
+    pub output: String,
+    pub attestation: Option<Attestation>,
+}
+
+#[cw_serde]
+pub struct BatchItemResult {
+    pub token_id: String,
+    pub task_id: String,
+    /// `requested`, `finalized` or `pending_quorum`
+    pub status: String,
+}
+
+/// Data set on batch responses, one result per item
+#[cw_serde]
+pub struct BatchResult {
+    pub items: Vec<BatchItemResult>,
+}
+
+/// Executor signature over `attestation_hash`, lets anyone relay the output
+#[cw_serde]
+pub struct Attestation {
+    pub executor: String,
+    pub signature: Binary,
+}
+
 #[cw_ownable_query]
 #[cw_serde]
 #[derive(QueryResponses)]
@@ -116,6 +313,205 @@ pub enum QueryMsg<Q: JsonSchema> {
 
     #[returns(IncompleteProjectsResponse)]
     IncompleteProjects {},
+
+    /// Return accepted denominations and prices of a project, empty if free
+    #[returns(PricesResponse)]
+    Prices { token_id: String },
+
+    /// Return the fee split, null if everything goes to the project owner
+    #[returns(Option<RevenueShare>)]
+    RevenueShare {},
+
+    /// Return the royalty owed on a sale of the token at `sale_price`, cw2981 compatible
+    #[returns(RoyaltiesInfoResponse)]
+    RoyaltyInfo {
+        token_id: String,
+        sale_price: Uint128,
+    },
+
+    /// Signal cw2981 support to marketplaces
+    #[returns(CheckRoyaltiesResponse)]
+    CheckRoyalties {},
+
+    /// Return the prepaid credits of an address
+    #[returns(BalancesResponse)]
+    Credits { address: String },
+
+    /// Return the subscription plans of a project, by index
+    #[returns(PlansResponse)]
+    Plans { token_id: String },
+
+    /// Return the subscription of an address to a project and what is left of its quota
+    #[returns(SubscriptionResponse)]
+    Subscription {
+        token_id: String,
+        subscriber: String,
+    },
+
+    /// Return which operations are paused and whether a project is suspended
+    #[returns(StatusResponse)]
+    Status { token_id: Option<String> },
+
+    /// Return the roles granted to an address, the contract owner implicitly holds all of them
+    #[returns(Vec<Role>)]
+    Roles { address: String },
+
+    /// Return the request limits of a project, null if unlimited
+    #[returns(Option<RateLimits>)]
+    RateLimits { token_id: String },
+
+    /// Return whether only the owner and license holders can request a project
+    #[returns(bool)]
+    Private { token_id: String },
+
+    /// List licenses to a project by holder
+    #[returns(LicensesResponse)]
+    Licenses {
+        token_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// List licenses held by an address by project
+    #[returns(LicensesResponse)]
+    HolderLicenses {
+        holder: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Return the terms licenses to a project are sold on, null if they are not sold
+    #[returns(Option<LicenseOffer>)]
+    LicenseOffer { token_id: String },
+
+    /// Return the listing of a token, null if it is not for sale
+    #[returns(Option<Listing>)]
+    Listing { token_id: String },
+
+    /// List tokens for sale by token id, only those of `seller` if set
+    #[returns(ListingsResponse)]
+    Listings {
+        seller: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// List tokens for sale in `denom`, cheapest first
+    #[returns(ListingsResponse)]
+    ListingsByPrice {
+        denom: String,
+        start_after: Option<ListingCursor>,
+        limit: Option<u32>,
+    },
+
+    /// Return the portion of marketplace sales taken for the treasury, null if none
+    #[returns(Option<Decimal>)]
+    MarketFee {},
+
+    /// Return the highest royalty percentage a project can be minted with
+    #[returns(u64)]
+    MaxRoyaltyPercentage {},
+
+    /// Return the portion of fork fees passed on to parents, null if none
+    #[returns(Option<Decimal>)]
+    LineageShare {},
+
+    /// Return the projects a project was forked from, nearest first and up to the first burned
+    /// one. `start_after` continues from an ancestor of a previous page
+    #[returns(LineageResponse)]
+    Ancestors {
+        token_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// List the forks of a project and their forks, depth first
+    #[returns(LineageResponse)]
+    Descendants {
+        token_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Return what `Withdraw` would pay the address, including fees accrued to a page of
+    /// its tokens
+    #[returns(BalancesResponse)]
+    Balances {
+        address: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Return Archway rewards pending distribution
+    #[returns(RewardsResponse)]
+    Rewards {},
+
+    /// Return the Archway flat fee charged for every execution of the contract
+    #[returns(archway_bindings::types::rewards::FlatFeeResponse)]
+    FlatFee {},
+
+    /// Return how many agreeing executors a project needs to finalize a task
+    #[returns(QuorumResponse)]
+    Quorum { token_id: String },
+
+    /// Return the executors allowed to work on a project
+    #[returns(AllowlistResponse)]
+    Allowlist { token_id: String },
+
+    /// Return a recurring request
+    #[returns(ScheduleResponse)]
+    Schedule { id: u64 },
+
+    /// List unpaused schedules whose next run is due, those due at a height first, then those due
+    /// at a time, each in the order they fell due
+    #[returns(SchedulesResponse)]
+    DueSchedules {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    #[returns(PipelineResponse)]
+    Pipeline { id: u64 },
+
+    /// Return a pipeline in progress, none once its last stage is finalized or cancelled.
+    /// A run whose next stage cannot be requested stays with the reason it failed
+    #[returns(Option<PipelineRun>)]
+    PipelineRun { id: u64 },
+
+    /// Return outputs submitted for a task still waiting on its quorum
+    #[returns(SubmissionsResponse)]
+    Submissions { token_id: String, task_id: String },
+
+    /// Return output hashes committed for a task and not revealed yet
+    #[returns(CommitmentsResponse)]
+    Commitments { token_id: String, task_id: String },
+
+    /// Return the executor staking configuration, null when staking is disabled
+    #[returns(Option<StakingConfig>)]
+    Staking {},
+
+    /// Return the bonded, unbonding and slashed amounts of an executor
+    #[returns(StakeResponse)]
+    Stake { address: String },
+
+    /// Return the executor that claimed a task, null when unclaimed
+    #[returns(Option<TaskClaim>)]
+    Claim { token_id: String, task_id: String },
+
+    /// Return the attestation key of an executor, null when unset
+    #[returns(Option<ExecutorKey>)]
+    ExecutorKey { address: String },
+
+    /// Return the track record of an executor
+    #[returns(ExecutorStatsResponse)]
+    ExecutorStats { address: String },
+
+    /// List registered executors by descending reliability, then completed tasks
+    #[returns(ExecutorsResponse)]
+    Executors {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 #[cw_ownable_execute]
@@ -168,20 +564,335 @@ pub enum ExecuteMsg<T, E> {
     /// Extension msg
     Extension { msg: E },
 
-    /// Request
-    Request { token_id: String, input: String },
+    /// Request, with the project price attached as native funds if it has one.
+    /// Executors cannot respond before `not_before` expires
+    Request {
+        token_id: String,
+        input: String,
+        not_before: Option<Expiration>,
+    },
 
-    /// Response
+    /// Response, only an admin or a registered executor can call it, or the
+    /// allowlisted executors if the project has an allowlist.
+    /// With a quorum above one, the output is recorded until enough executors agree on it.
+    /// With an attestation, anyone can submit the output on behalf of its executor
     Response {
         token_id: String,
         task_id: String,
         output: String,
+        attestation: Option<Attestation>,
     },
 
-    /// Update
+    /// Fill in the missing title and description of a project, only a describer can call it
     Update {
         token_id: String,
         title: String,
         description: String,
     },
+
+    /// Request paid with cw20 tokens, the embedded msg is a `ReceiveMsg`
+    Receive(Cw20ReceiveMsg),
+
+    /// Withdraw an unresponded request and refund its payment
+    CancelRequest { token_id: String, task_id: String },
+
+    /// Set accepted denominations and prices, only the project owner can call it
+    SetPrices {
+        token_id: String,
+        prices: Vec<Price>,
+    },
+
+    /// Set how request fees are split, only a treasurer can call it
+    SetRevenueShare {
+        owner: Decimal,
+        executor: Decimal,
+        treasury: Decimal,
+        treasury_address: String,
+    },
+
+    /// Add the attached native funds to the sender's credits, which pay for requests sent
+    /// without funds
+    DepositCredits {},
+
+    /// Withdraw `amount` of the sender's credits, or all of them when unset
+    WithdrawCredits { amount: Option<Coin> },
+
+    /// Replace the subscription plans of a project, existing subscriptions keep their terms.
+    /// Only the token owner can call it
+    SetPlans { token_id: String, plans: Vec<Plan> },
+
+    /// Pay for one period of a plan with its price attached. Subscribing again to the same
+    /// plan adds a period on the same terms, switching plans is only possible once the current
+    /// one lapsed
+    Subscribe { token_id: String, plan: u32 },
+
+    /// End the sender's subscription right away, unused time is not refunded
+    Unsubscribe { token_id: String },
+
+    /// Limit how many tasks each requester and all of them together can have pending or
+    /// request per block window, removing the limits when both are unset. Only the token
+    /// owner can call it
+    SetRateLimits {
+        token_id: String,
+        per_address: Option<RateLimit>,
+        per_project: Option<RateLimit>,
+        exempt: Vec<String>,
+    },
+
+    /// Only let the owner and license holders request a project, only the token owner can
+    /// call it
+    SetPrivate { token_id: String, private: bool },
+
+    /// Grant `holder` requests to a project until `expires`, `uses` times if set. Replaces
+    /// any license the holder has, only the token owner can call it
+    IssueLicense {
+        token_id: String,
+        holder: String,
+        expires: Option<Expiration>,
+        uses: Option<u32>,
+    },
+
+    /// Only the token owner can call it
+    RevokeLicense { token_id: String, holder: String },
+
+    /// Sell licenses to a project on these terms, or stop selling them when unset. Only the
+    /// token owner can call it
+    SetLicenseOffer {
+        token_id: String,
+        offer: Option<LicenseOffer>,
+    },
+
+    /// Buy a license with the offer price attached, the price accrues to the token. Fails while
+    /// the sender's license to the project is still valid
+    BuyLicense { token_id: String },
+
+    /// Put a token up for sale, the contract holds it until it is bought or delisted. Its
+    /// fees and reward points keep going to the seller meanwhile
+    ListNft { token_id: String, price: Coin },
+
+    /// Take a token off sale and give it back to the seller
+    DelistNft { token_id: String },
+
+    /// Buy a listed token with its price attached, paying royalties and the market fee out
+    /// of it when they have a recipient. Sellers cannot buy their own listing
+    BuyNft { token_id: String },
+
+    /// Set the portion of marketplace sales taken for the treasury, only a treasurer can
+    /// call it
+    SetMarketFee { fee: Decimal },
+
+    /// Cap the royalty percentage of projects minted from now on, only an admin can call it
+    SetMaxRoyaltyPercentage { percentage: u64 },
+
+    /// Set the portion of its fee share each fork passes on to its parent, only a treasurer
+    /// can call it. Fees go up to 10 ancestors and stop at burned ones, the last ancestor
+    /// reached keeps the rest
+    SetLineageShare { share: Decimal },
+
+    /// Mint a copy of a project to the sender, with `code` replacing the parent code if set
+    Fork {
+        token_id: String,
+        code: Option<String>,
+    },
+
+    /// Withdraw the sender's balance and fees accrued to the tokens it owns, paging through
+    /// them like the `Tokens` query
+    Withdraw {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Withdraw the contract's Archway rewards and credit them to executors and
+    /// project owners in proportion to completed tasks, only a treasurer can call it
+    DistributeRewards {},
+
+    /// Set the Archway flat fee charged for every execution of the contract, the contract
+    /// must be the owner in its rewards metadata. Only a treasurer can call it
+    SetFlatFee { amount: Coin },
+
+    /// Register an executor, only an executor manager can call it
+    AddExecutor { address: String },
+
+    /// Unregister an executor, only an executor manager can call it
+    RemoveExecutor { address: String },
+
+    /// Require `quorum` executors to agree on an output, only the project owner can call it
+    SetQuorum { token_id: String, quorum: u32 },
+
+    /// Make executors commit to `commitment_hash`es for `blocks` after each request before
+    /// revealing their outputs, only the project owner can call it. Zero disables it
+    SetCommitWindow { token_id: String, blocks: u64 },
+
+    /// Commit to an output during the commit window
+    CommitOutput {
+        token_id: String,
+        task_id: String,
+        hash: HexBinary,
+    },
+
+    /// Reveal a committed output once the commit window is over
+    RevealOutput {
+        token_id: String,
+        task_id: String,
+        output: String,
+        salt: String,
+    },
+
+    /// Require executors to bond stake, only an executor manager can call it.
+    /// The denom cannot change once set
+    SetStaking {
+        denom: String,
+        min_bond: Uint128,
+        unbonding_period: u64,
+        /// Portion of the stake taken for the treasury on each slash, nothing is taken while
+        /// the contract has no treasury
+        slash_ratio: Decimal,
+        response_timeout: u64,
+    },
+
+    /// Bond the attached stake, registering the sender as an executor once it reaches
+    /// the minimum bond
+    RegisterExecutor {},
+
+    /// Start unbonding stake, claimable after the unbonding period
+    Unbond { amount: Uint128 },
+
+    /// Withdraw stake whose unbonding period is over
+    ClaimUnbonded {},
+
+    /// Reserve a single-executor task for `response_timeout` blocks. The claim is slashed
+    /// if the task is not responded in time
+    ClaimTask { token_id: String, task_id: String },
+
+    /// Slash the executor of an expired claim, anyone can call it. Blocks during which responses
+    /// were paused or the project suspended do not count toward the claim expiry
+    SlashExpiredClaim { token_id: String, task_id: String },
+
+    /// Slash an executor that did not reveal its commitment in time, anyone can call it.
+    /// Finalized tasks and tasks due at a time rather than a height cannot be slashed for, and
+    /// blocks during which responses were paused or the project suspended extend the deadline
+    SlashUnrevealed {
+        token_id: String,
+        task_id: String,
+        executor: String,
+    },
+
+    /// Restrict a project to the given executors instead of the registered ones, only the
+    /// project owner can call it. An empty list lifts the restriction
+    SetAllowlist {
+        token_id: String,
+        executors: Vec<String>,
+    },
+
+    /// Request `input` every `interval` up to `max_runs` times, with the project price of
+    /// every run attached as native funds if it has one. Without funds, each run is paid from
+    /// the owner's subscription or credits when it triggers
+    ScheduleRequest {
+        token_id: String,
+        input: String,
+        interval: Interval,
+        max_runs: u32,
+    },
+
+    /// Create the task of a due schedule, anyone can call it
+    TriggerSchedule { id: u64 },
+
+    /// Stop triggering a schedule, only its owner can call it
+    PauseSchedule { id: u64 },
+
+    /// Resume a paused schedule, only its owner can call it
+    ResumeSchedule { id: u64 },
+
+    /// Remove a schedule and refund its remaining runs, only its owner can call it
+    CancelSchedule { id: u64 },
+
+    /// Create several requests at once, with the sum of their prices attached as native
+    /// funds or, without funds, each paid from the sender's subscription or credits. Fails as
+    /// a whole if any item fails
+    BatchRequest { items: Vec<RequestItem> },
+
+    /// Submit several outputs at once. Fails as a whole if any item fails
+    BatchResponse { items: Vec<ResponseItem> },
+
+    /// Chain projects so that finalizing a task of one stage requests the next stage
+    CreatePipeline {
+        stages: Vec<PipelineStage>,
+        destination: Option<String>,
+    },
+
+    /// Request the first stage of a pipeline with `input`, with the prices of every stage
+    /// attached as native funds or, without funds, each paid upfront from the sender's
+    /// subscription or credits
+    RunPipeline { id: u64, input: String },
+
+    /// Set the key the sender signs attestations with
+    SetExecutorKey { scheme: KeyScheme, pubkey: Binary },
+
+    /// Pause or unpause operations, leaving unset ones as they are. Only an admin can call it
+    SetPauses {
+        minting: Option<bool>,
+        requests: Option<bool>,
+        responses: Option<bool>,
+        transfers: Option<bool>,
+    },
+
+    /// Stop new requests and responses to a project, only the token owner or a moderator
+    /// can call it
+    Suspend { token_id: String },
+
+    /// Lift the suspension of a project, only a moderator or, unless a moderator suspended it,
+    /// the token owner can call it
+    Unsuspend { token_id: String },
+
+    /// Grant a role, only the contract owner or an admin can call it
+    GrantRole { address: String, role: Role },
+
+    /// Revoke a role, only the contract owner or an admin can call it
+    RevokeRole { address: String, role: Role },
+}
+
+/// Messages embedded in `Cw20ReceiveMsg.msg`
+#[cw_serde]
+pub enum ReceiveMsg {
+    Request {
+        token_id: String,
+        input: String,
+        not_before: Option<Expiration>,
+    },
+}
+
+/// SHA-256 of the fields, each prefixed by its length as a 4-byte big-endian integer so
+/// that no two lists of fields share a preimage
+fn fields_hash(fields: &[&[u8]]) -> HexBinary {
+    let mut hasher = Sha256::new();
+    for field in fields {
+        hasher.update((field.len() as u32).to_be_bytes());
+        hasher.update(field);
+    }
+    HexBinary::from(hasher.finalize().to_vec())
+}
+
+/// Hash an executor commits to before revealing `output`
+pub fn commitment_hash(executor: &str, salt: &str, output: &str) -> HexBinary {
+    fields_hash(&[executor.as_bytes(), salt.as_bytes(), output.as_bytes()])
+}
+
+/// Hash an executor signs to attest `output`, `code_hash` and `input_hash` being the
+/// SHA-256 of the project code and of the task input
+pub fn attestation_hash(
+    contract: &str,
+    token_id: &str,
+    task_id: &str,
+    code_hash: &HexBinary,
+    input_hash: &HexBinary,
+    output: &str,
+) -> HexBinary {
+    fields_hash(&[
+        contract.as_bytes(),
+        token_id.as_bytes(),
+        task_id.as_bytes(),
+        code_hash.as_slice(),
+        input_hash.as_slice(),
+        output.as_bytes(),
+    ])
 }
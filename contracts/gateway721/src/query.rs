@@ -1,12 +1,77 @@
 use cw721_base::state::TokenInfo;
 use schemars::JsonSchema;
 
-use cosmwasm_std::{to_binary, Binary, CustomMsg, Deps, Env, StdResult};
+use archway_bindings::types::rewards::{FlatFeeResponse, RewardsRecordsResponse};
+use archway_bindings::{ArchwayMsg, ArchwayQuery};
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Coin, CustomMsg, Decimal, Deps, Env, Order, StdError, StdResult,
+    Storage, Uint128,
+};
 
-use crate::msg::{IncompleteProjectsResponse, QueryMsg, TaskIdsResponse};
-use crate::state::{Extension, Gateway721Contract};
+use crate::msg::{
+    AllowlistResponse, BalancesResponse, CheckRoyaltiesResponse, Commitment, CommitmentsResponse,
+    ExecutorStatsResponse, ExecutorsResponse, IncompleteProjectsResponse, LicenseResponse,
+    LicensesResponse, LineageResponse, ListingCursor, ListingResponse, ListingsResponse,
+    PipelineResponse, PlansResponse, PricesResponse, QueryMsg, QuorumResponse, RewardsResponse,
+    RoyaltiesInfoResponse, ScheduleResponse, SchedulesResponse, StakeResponse, StatusResponse,
+    SubmissionsResponse, SubscriptionResponse, TaskIdsResponse,
+};
+use crate::state::{
+    add_balance, Balance, ExecutorKey, Extension, Gateway721Contract, LicenseOffer, Listing,
+    PipelineRun, RateLimits, RevenueShare, Role, StakingConfig, TaskClaim, DUE_AT_HEIGHT,
+    DUE_AT_TIME,
+};
 use crate::traits::Gateway721Query;
 use cw721_base::QueryMsg as Cw721QueryMsg;
+use cw_storage_plus::Bound;
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+impl<'a, E, Q> Gateway721Contract<'a, Extension, ArchwayMsg, E, Q>
+where
+    E: CustomMsg,
+    Q: CustomMsg,
+{
+    pub fn query(&self, deps: Deps<ArchwayQuery>, env: Env, msg: QueryMsg<Q>) -> StdResult<Binary> {
+        match msg {
+            QueryMsg::Rewards {} => to_json_binary(&self.rewards(deps, env)?),
+            QueryMsg::FlatFee {} => to_json_binary(&self.flat_fee(deps, env)?),
+            _ => self.query_base(deps.into_empty(), env, msg),
+        }
+    }
+
+    fn flat_fee(&self, deps: Deps<ArchwayQuery>, env: Env) -> StdResult<FlatFeeResponse> {
+        deps.querier
+            .query(&ArchwayQuery::flat_fee(env.contract.address).into())
+    }
+
+    fn rewards(&self, deps: Deps<ArchwayQuery>, env: Env) -> StdResult<RewardsResponse> {
+        let records: RewardsRecordsResponse = deps
+            .querier
+            .query(&ArchwayQuery::rewards_records(env.contract.address).into())?;
+
+        let mut pending: Vec<Coin> = vec![];
+        for coin in records
+            .records
+            .into_iter()
+            .flat_map(|record| record.rewards)
+        {
+            match pending.iter_mut().find(|c| c.denom == coin.denom) {
+                Some(c) => c.amount += coin.amount,
+                None => pending.push(coin),
+            }
+        }
+
+        Ok(RewardsResponse {
+            pending,
+            total_points: self
+                .reward_points_total
+                .may_load(deps.storage)?
+                .unwrap_or_default(),
+        })
+    }
+}
 
 impl<'a, C, E, Q> Gateway721Contract<'a, Extension, C, E, Q>
 where
@@ -14,13 +79,207 @@ where
     E: CustomMsg,
     Q: CustomMsg,
 {
-    pub fn query(&self, deps: Deps, env: Env, msg: QueryMsg<Q>) -> StdResult<Binary> {
+    /// Handles every query that does not need the Archway bindings
+    pub fn query_base(&self, deps: Deps, env: Env, msg: QueryMsg<Q>) -> StdResult<Binary> {
         match msg {
-            QueryMsg::Remains { token_id } => to_binary(&self.remains(deps, token_id)?),
-            QueryMsg::IncompleteProjects {} => to_binary(&self.incomplete_projects(deps)?),
+            QueryMsg::Remains { token_id } => to_json_binary(&self.remains(deps, env, token_id)?),
+            QueryMsg::IncompleteProjects {} => to_json_binary(&self.incomplete_projects(deps)?),
+            QueryMsg::Prices { token_id } => to_json_binary(&self.prices(deps, token_id)?),
+            QueryMsg::RevenueShare {} => to_json_binary(&self.revenue_share(deps)?),
+            QueryMsg::RoyaltyInfo {
+                token_id,
+                sale_price,
+            } => to_json_binary(&self.royalty_info(deps, token_id, sale_price)?),
+            QueryMsg::CheckRoyalties {} => to_json_binary(&self.check_royalties(deps)?),
+            QueryMsg::Credits { address } => to_json_binary(&self.credits(deps, address)?),
+            QueryMsg::Plans { token_id } => to_json_binary(&self.plans(deps, token_id)?),
+            QueryMsg::Subscription {
+                token_id,
+                subscriber,
+            } => to_json_binary(&self.subscription(deps, env, token_id, subscriber)?),
+            QueryMsg::Status { token_id } => to_json_binary(&self.status(deps, token_id)?),
+            QueryMsg::Roles { address } => to_json_binary(&self.roles(deps, address)?),
+            QueryMsg::RateLimits { token_id } => to_json_binary(&self.rate_limits(deps, token_id)?),
+            QueryMsg::Private { token_id } => to_json_binary(&self.private(deps, token_id)?),
+            QueryMsg::Licenses {
+                token_id,
+                start_after,
+                limit,
+            } => to_json_binary(&self.licenses(deps, token_id, start_after, limit)?),
+            QueryMsg::HolderLicenses {
+                holder,
+                start_after,
+                limit,
+            } => to_json_binary(&self.holder_licenses(deps, holder, start_after, limit)?),
+            QueryMsg::LicenseOffer { token_id } => {
+                to_json_binary(&self.license_offer(deps, token_id)?)
+            }
+            QueryMsg::Listing { token_id } => to_json_binary(&self.listing(deps, token_id)?),
+            QueryMsg::Listings {
+                seller,
+                start_after,
+                limit,
+            } => to_json_binary(&self.listings(deps, seller, start_after, limit)?),
+            QueryMsg::ListingsByPrice {
+                denom,
+                start_after,
+                limit,
+            } => to_json_binary(&self.listings_by_price(deps, denom, start_after, limit)?),
+            QueryMsg::MarketFee {} => to_json_binary(&self.market_fee(deps)?),
+            QueryMsg::MaxRoyaltyPercentage {} => {
+                to_json_binary(&self.max_royalty_percentage(deps)?)
+            }
+            QueryMsg::LineageShare {} => to_json_binary(&self.lineage_share(deps)?),
+            QueryMsg::Ancestors {
+                token_id,
+                start_after,
+                limit,
+            } => to_json_binary(&self.ancestors(deps, token_id, start_after, limit)?),
+            QueryMsg::Descendants {
+                token_id,
+                start_after,
+                limit,
+            } => to_json_binary(&self.descendants(deps, token_id, start_after, limit)?),
+            QueryMsg::Balances {
+                address,
+                start_after,
+                limit,
+            } => to_json_binary(&self.balances(deps, address, start_after, limit)?),
+            QueryMsg::Quorum { token_id } => to_json_binary(&self.quorum(deps, token_id)?),
+            QueryMsg::Allowlist { token_id } => to_json_binary(&self.allowlist(deps, token_id)?),
+            QueryMsg::Schedule { id } => to_json_binary(&self.schedule(deps, id)?),
+            QueryMsg::DueSchedules { start_after, limit } => {
+                to_json_binary(&self.due_schedules(deps, env, start_after, limit)?)
+            }
+            QueryMsg::Pipeline { id } => to_json_binary(&self.pipeline(deps, id)?),
+            QueryMsg::PipelineRun { id } => to_json_binary(&self.pipeline_run(deps, id)?),
+            QueryMsg::Submissions { token_id, task_id } => {
+                to_json_binary(&self.submissions(deps, token_id, task_id)?)
+            }
+            QueryMsg::Commitments { token_id, task_id } => {
+                to_json_binary(&self.commitments(deps, token_id, task_id)?)
+            }
+            QueryMsg::Staking {} => to_json_binary(&self.staking(deps)?),
+            QueryMsg::Stake { address } => to_json_binary(&self.stake(deps, address)?),
+            QueryMsg::Claim { token_id, task_id } => {
+                to_json_binary(&self.claim(deps, token_id, task_id)?)
+            }
+            QueryMsg::ExecutorKey { address } => to_json_binary(&self.executor_key(deps, address)?),
+            QueryMsg::ExecutorStats { address } => {
+                to_json_binary(&self.executor_stats(deps, address)?)
+            }
+            QueryMsg::Executors { start_after, limit } => {
+                to_json_binary(&self.executors(deps, start_after, limit)?)
+            }
+            QueryMsg::Rewards {} | QueryMsg::FlatFee {} => Err(StdError::generic_err(
+                "Message requires the Archway bindings",
+            )),
             _ => self.cw721.query(deps, env, msg.into()),
         }
     }
+
+    fn executor_stats_response(
+        &self,
+        storage: &dyn Storage,
+        address: Addr,
+    ) -> StdResult<ExecutorStatsResponse> {
+        let stats = self
+            .executor_stats
+            .may_load(storage, &address)?
+            .unwrap_or_default();
+        let average_latency = stats.total_latency_blocks.checked_div(stats.completed);
+        let reliability = stats.reliability();
+        Ok(ExecutorStatsResponse {
+            address,
+            stats,
+            average_latency,
+            reliability,
+        })
+    }
+
+    /// The fork of `parent` following `after`, or its first fork
+    fn next_fork(
+        &self,
+        storage: &dyn Storage,
+        parent: &str,
+        after: Option<&str>,
+    ) -> StdResult<Option<String>> {
+        self.forks
+            .prefix(parent)
+            .keys(storage, after.map(Bound::exclusive), None, Order::Ascending)
+            .next()
+            .transpose()
+    }
+
+    /// The descendant of `root` following `current` depth first, walking back up the
+    /// lineage once `current` has no forks left
+    fn next_descendant(
+        &self,
+        storage: &dyn Storage,
+        root: &str,
+        current: String,
+    ) -> StdResult<Option<String>> {
+        if let Some(fork) = self.next_fork(storage, &current, None)? {
+            return Ok(Some(fork));
+        }
+        let mut current = current;
+        while current != root {
+            let parent = match self.cw721.tokens.may_load(storage, &current)? {
+                Some(token) => token.extension.and_then(|metadata| metadata.parent),
+                None => None,
+            };
+            let Some(parent) = parent else {
+                return Ok(None);
+            };
+            if let Some(sibling) = self.next_fork(storage, &parent, Some(&current))? {
+                return Ok(Some(sibling));
+            }
+            current = parent;
+        }
+        Ok(None)
+    }
+
+    /// Balance of `address` plus fees accrued to a page of the tokens it owns,
+    /// along with the ids of those tokens
+    pub(crate) fn claimable(
+        &self,
+        storage: &dyn Storage,
+        address: &Addr,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<(Vec<Balance>, Vec<String>)> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let mut balances = self
+            .balances
+            .may_load(storage, address)?
+            .unwrap_or_default();
+
+        let token_ids = self
+            .cw721
+            .tokens
+            .idx
+            .owner
+            .prefix(address.clone())
+            .keys(
+                storage,
+                start_after.map(|token_id| Bound::ExclusiveRaw(token_id.into())),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+        let mut accrued = vec![];
+        for token_id in token_ids {
+            if let Some(token_balances) = self.token_balances.may_load(storage, &token_id)? {
+                for balance in token_balances {
+                    add_balance(&mut balances, &balance.denom, balance.amount);
+                }
+                accrued.push(token_id);
+            }
+        }
+
+        Ok((balances, accrued))
+    }
 }
 
 impl<'a, C, E, Q> Gateway721Query<Extension> for Gateway721Contract<'a, Extension, C, E, Q>
@@ -29,7 +288,7 @@ where
     E: CustomMsg,
     Q: CustomMsg,
 {
-    fn remains(&self, deps: Deps, token_id: String) -> StdResult<TaskIdsResponse> {
+    fn remains(&self, deps: Deps, env: Env, token_id: String) -> StdResult<TaskIdsResponse> {
         let token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
 
         // Collect unresponded task IDs
@@ -37,7 +296,12 @@ where
             if let Some(tasks) = metadata.tasks {
                 tasks
                     .iter()
-                    .filter(|task| task.output.is_none())
+                    .filter(|task| task.output.is_none() && !task.cancelled)
+                    .filter(|task| !self.fan_outs.has(deps.storage, (&token_id, &task.tid)))
+                    .filter(|task| {
+                        task.not_before
+                            .map_or(true, |not_before| not_before.is_expired(&env.block))
+                    })
                     .map(|task| task.tid.clone())
                     .collect()
             } else {
@@ -53,6 +317,496 @@ where
     fn incomplete_projects(&self, deps: Deps) -> StdResult<IncompleteProjectsResponse> {
         self.incomplete_projects.load(deps.storage)
     }
+
+    fn prices(&self, deps: Deps, token_id: String) -> StdResult<PricesResponse> {
+        let prices = self
+            .prices
+            .may_load(deps.storage, &token_id)?
+            .unwrap_or_default();
+        Ok(PricesResponse { prices })
+    }
+
+    fn revenue_share(&self, deps: Deps) -> StdResult<Option<RevenueShare>> {
+        self.revenue_share.may_load(deps.storage)
+    }
+
+    fn royalty_info(
+        &self,
+        deps: Deps,
+        token_id: String,
+        sale_price: Uint128,
+    ) -> StdResult<RoyaltiesInfoResponse> {
+        let token = self.cw721.tokens.load(deps.storage, &token_id)?;
+        let metadata = token.extension.unwrap_or_default();
+        let royalty_amount =
+            sale_price.multiply_ratio(metadata.royalty_percentage.unwrap_or_default(), 100u128);
+        let address = metadata
+            .royalty_payment_address
+            .map(String::from)
+            .unwrap_or_default();
+        Ok(RoyaltiesInfoResponse {
+            address,
+            royalty_amount,
+        })
+    }
+
+    fn check_royalties(&self, _deps: Deps) -> StdResult<CheckRoyaltiesResponse> {
+        Ok(CheckRoyaltiesResponse {
+            royalty_payments: true,
+        })
+    }
+
+    fn credits(&self, deps: Deps, address: String) -> StdResult<BalancesResponse> {
+        let address = deps.api.addr_validate(&address)?;
+        let balances = self
+            .credits
+            .may_load(deps.storage, &address)?
+            .unwrap_or_default();
+        Ok(BalancesResponse { balances })
+    }
+
+    fn plans(&self, deps: Deps, token_id: String) -> StdResult<PlansResponse> {
+        let plans = self
+            .plans
+            .may_load(deps.storage, &token_id)?
+            .unwrap_or_default();
+        Ok(PlansResponse { plans })
+    }
+
+    fn subscription(
+        &self,
+        deps: Deps,
+        env: Env,
+        token_id: String,
+        subscriber: String,
+    ) -> StdResult<SubscriptionResponse> {
+        let subscriber = deps.api.addr_validate(&subscriber)?;
+        let Some(mut subscription) = self
+            .subscriptions
+            .may_load(deps.storage, (&token_id, &subscriber))?
+        else {
+            return Ok(SubscriptionResponse {
+                subscription: None,
+                active: false,
+                remaining: None,
+            });
+        };
+
+        subscription.roll(&env.block);
+        Ok(SubscriptionResponse {
+            active: subscription.is_active(&env.block),
+            remaining: subscription.remaining(),
+            subscription: Some(subscription),
+        })
+    }
+
+    fn status(&self, deps: Deps, token_id: Option<String>) -> StdResult<StatusResponse> {
+        let pauses = self.pauses.may_load(deps.storage)?.unwrap_or_default();
+        let suspension = match token_id {
+            Some(token_id) => self.suspended.may_load(deps.storage, &token_id)?,
+            None => None,
+        };
+        Ok(StatusResponse { pauses, suspension })
+    }
+
+    fn roles(&self, deps: Deps, address: String) -> StdResult<Vec<Role>> {
+        let address = deps.api.addr_validate(&address)?;
+        Ok(self
+            .roles
+            .may_load(deps.storage, &address)?
+            .unwrap_or_default())
+    }
+
+    fn rate_limits(&self, deps: Deps, token_id: String) -> StdResult<Option<RateLimits>> {
+        self.rate_limits.may_load(deps.storage, &token_id)
+    }
+
+    fn private(&self, deps: Deps, token_id: String) -> StdResult<bool> {
+        Ok(self.private_projects.has(deps.storage, &token_id))
+    }
+
+    fn licenses(
+        &self,
+        deps: Deps,
+        token_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<LicensesResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start_after = start_after
+            .map(|holder| deps.api.addr_validate(&holder))
+            .transpose()?;
+
+        let licenses = self
+            .licenses
+            .prefix(&token_id)
+            .range(
+                deps.storage,
+                start_after.as_ref().map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .map(|item| {
+                item.map(|(_, license)| LicenseResponse {
+                    token_id: token_id.clone(),
+                    license,
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok(LicensesResponse { licenses })
+    }
+
+    fn holder_licenses(
+        &self,
+        deps: Deps,
+        holder: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<LicensesResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let holder = deps.api.addr_validate(&holder)?;
+        let start = start_after.map(|token_id| Bound::exclusive((token_id, holder.clone())));
+
+        let licenses = self
+            .licenses
+            .idx
+            .holder
+            .prefix(holder)
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|((token_id, _), license)| LicenseResponse { token_id, license }))
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok(LicensesResponse { licenses })
+    }
+
+    fn license_offer(&self, deps: Deps, token_id: String) -> StdResult<Option<LicenseOffer>> {
+        self.license_offers.may_load(deps.storage, &token_id)
+    }
+
+    fn listing(&self, deps: Deps, token_id: String) -> StdResult<Option<Listing>> {
+        self.listings.may_load(deps.storage, &token_id)
+    }
+
+    fn listings(
+        &self,
+        deps: Deps,
+        seller: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<ListingsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+        let listings = match seller {
+            Some(seller) => {
+                let seller = deps.api.addr_validate(&seller)?;
+                self.listings
+                    .idx
+                    .seller
+                    .prefix(seller)
+                    .range(
+                        deps.storage,
+                        start_after.map(Bound::exclusive),
+                        None,
+                        Order::Ascending,
+                    )
+                    .take(limit)
+                    .map(|item| {
+                        item.map(|(token_id, listing)| ListingResponse { token_id, listing })
+                    })
+                    .collect::<StdResult<Vec<_>>>()?
+            }
+            None => self
+                .listings
+                .range(
+                    deps.storage,
+                    start_after.as_deref().map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(limit)
+                .map(|item| item.map(|(token_id, listing)| ListingResponse { token_id, listing }))
+                .collect::<StdResult<Vec<_>>>()?,
+        };
+        Ok(ListingsResponse { listings })
+    }
+
+    fn listings_by_price(
+        &self,
+        deps: Deps,
+        denom: String,
+        start_after: Option<ListingCursor>,
+        limit: Option<u32>,
+    ) -> StdResult<ListingsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start =
+            start_after.map(|cursor| Bound::exclusive((cursor.amount.u128(), cursor.token_id)));
+
+        let listings = self
+            .listings
+            .idx
+            .price
+            .sub_prefix(denom)
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(token_id, listing)| ListingResponse { token_id, listing }))
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok(ListingsResponse { listings })
+    }
+
+    fn market_fee(&self, deps: Deps) -> StdResult<Option<Decimal>> {
+        self.market_fee.may_load(deps.storage)
+    }
+
+    fn max_royalty_percentage(&self, deps: Deps) -> StdResult<u64> {
+        Ok(self
+            .max_royalty_percentage
+            .may_load(deps.storage)?
+            .unwrap_or(100))
+    }
+
+    fn lineage_share(&self, deps: Deps) -> StdResult<Option<Decimal>> {
+        self.lineage_share.may_load(deps.storage)
+    }
+
+    fn ancestors(
+        &self,
+        deps: Deps,
+        token_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<LineageResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+        let mut token_ids = vec![];
+        let mut current = start_after.unwrap_or(token_id);
+        while token_ids.len() < limit {
+            let Some(parent) = self.parent(deps.storage, &current)? else {
+                break;
+            };
+            token_ids.push(parent.clone());
+            current = parent;
+        }
+        Ok(LineageResponse { token_ids })
+    }
+
+    fn descendants(
+        &self,
+        deps: Deps,
+        token_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<LineageResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+        let mut token_ids: Vec<String> = vec![];
+        let mut next = match start_after {
+            Some(start_after) => self.next_descendant(deps.storage, &token_id, start_after)?,
+            None => self.next_fork(deps.storage, &token_id, None)?,
+        };
+        while let Some(current) = next {
+            if token_ids.len() == limit {
+                break;
+            }
+            token_ids.push(current.clone());
+            next = self.next_descendant(deps.storage, &token_id, current)?;
+        }
+        Ok(LineageResponse { token_ids })
+    }
+
+    fn balances(
+        &self,
+        deps: Deps,
+        address: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<BalancesResponse> {
+        let address = deps.api.addr_validate(&address)?;
+        let (balances, _) = self.claimable(deps.storage, &address, start_after, limit)?;
+        Ok(BalancesResponse { balances })
+    }
+
+    fn quorum(&self, deps: Deps, token_id: String) -> StdResult<QuorumResponse> {
+        let quorum = self.quorums.may_load(deps.storage, &token_id)?.unwrap_or(1);
+        Ok(QuorumResponse { quorum })
+    }
+
+    fn allowlist(&self, deps: Deps, token_id: String) -> StdResult<AllowlistResponse> {
+        let executors = self
+            .allowlists
+            .may_load(deps.storage, &token_id)?
+            .unwrap_or_default();
+        Ok(AllowlistResponse { executors })
+    }
+
+    fn schedule(&self, deps: Deps, id: u64) -> StdResult<ScheduleResponse> {
+        let schedule = self.schedules.load(deps.storage, id)?;
+        Ok(ScheduleResponse { id, schedule })
+    }
+
+    fn pipeline(&self, deps: Deps, id: u64) -> StdResult<PipelineResponse> {
+        let pipeline = self.pipelines.load(deps.storage, id)?;
+        Ok(PipelineResponse { id, pipeline })
+    }
+
+    fn pipeline_run(&self, deps: Deps, id: u64) -> StdResult<Option<PipelineRun>> {
+        self.pipeline_runs.may_load(deps.storage, id)
+    }
+
+    fn due_schedules(
+        &self,
+        deps: Deps,
+        env: Env,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<SchedulesResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        // pages follow the due index, starting over once the given schedule is gone
+        let start = match start_after {
+            Some(id) => self
+                .schedules
+                .may_load(deps.storage, id)?
+                .map(|schedule| (schedule.due_key(), id)),
+            None => None,
+        };
+
+        let mut schedules = vec![];
+        for (kind, now) in [
+            (DUE_AT_HEIGHT, env.block.height),
+            (DUE_AT_TIME, env.block.time.nanos()),
+        ] {
+            let min = match start {
+                Some(((start_kind, _), _)) if start_kind > kind => continue,
+                Some(((start_kind, due), id)) if start_kind == kind => {
+                    Some(Bound::exclusive((due, id)))
+                }
+                _ => None,
+            };
+            let max = Some(Bound::inclusive((now, u64::MAX)));
+            let due = self
+                .schedules
+                .idx
+                .due
+                .sub_prefix(kind)
+                .range(deps.storage, min, max, Order::Ascending)
+                .take(limit - schedules.len())
+                .map(|item| item.map(|(id, schedule)| ScheduleResponse { id, schedule }))
+                .collect::<StdResult<Vec<_>>>()?;
+            schedules.extend(due);
+        }
+        Ok(SchedulesResponse { schedules })
+    }
+
+    fn submissions(
+        &self,
+        deps: Deps,
+        token_id: String,
+        task_id: String,
+    ) -> StdResult<SubmissionsResponse> {
+        let submissions = self
+            .submissions
+            .may_load(deps.storage, (&token_id, &task_id))?
+            .unwrap_or_default();
+        Ok(SubmissionsResponse { submissions })
+    }
+
+    fn commitments(
+        &self,
+        deps: Deps,
+        token_id: String,
+        task_id: String,
+    ) -> StdResult<CommitmentsResponse> {
+        let token: TokenInfo<Extension> = self.cw721.tokens.load(deps.storage, &token_id)?;
+        let commit_deadline = match token
+            .extension
+            .and_then(|metadata| metadata.tasks)
+            .and_then(|tasks| tasks.into_iter().find(|task| task.tid == task_id))
+        {
+            Some(task) => self.commit_deadline(deps.storage, &token_id, &task)?,
+            None => None,
+        };
+
+        let commitments = self
+            .commitments
+            .prefix((&token_id, &task_id))
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(executor, hash)| Commitment { executor, hash }))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(CommitmentsResponse {
+            commit_deadline,
+            commitments,
+        })
+    }
+
+    fn staking(&self, deps: Deps) -> StdResult<Option<StakingConfig>> {
+        self.staking.may_load(deps.storage)
+    }
+
+    fn stake(&self, deps: Deps, address: String) -> StdResult<StakeResponse> {
+        let address = deps.api.addr_validate(&address)?;
+        let stake = self
+            .stakes
+            .may_load(deps.storage, &address)?
+            .unwrap_or_default();
+        let active = self.executors.has(deps.storage, &address)
+            && self
+                .staking
+                .may_load(deps.storage)?
+                .map_or(true, |config| stake.bonded >= config.min_bond);
+        Ok(StakeResponse { stake, active })
+    }
+
+    fn claim(&self, deps: Deps, token_id: String, task_id: String) -> StdResult<Option<TaskClaim>> {
+        self.claims.may_load(deps.storage, (&token_id, &task_id))
+    }
+
+    fn executor_key(&self, deps: Deps, address: String) -> StdResult<Option<ExecutorKey>> {
+        let address = deps.api.addr_validate(&address)?;
+        self.executor_keys.may_load(deps.storage, &address)
+    }
+
+    fn executor_stats(&self, deps: Deps, address: String) -> StdResult<ExecutorStatsResponse> {
+        let address = deps.api.addr_validate(&address)?;
+        self.executor_stats_response(deps.storage, address)
+    }
+
+    fn executors(
+        &self,
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<ExecutorsResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+        let start = start_after
+            .map(|address| -> StdResult<_> {
+                let address = deps.api.addr_validate(&address)?;
+                let stats = self
+                    .executor_stats
+                    .may_load(deps.storage, &address)?
+                    .unwrap_or_default();
+                Ok((stats.rank(), address))
+            })
+            .transpose()?;
+        let executors = self
+            .executor_ranks
+            .keys(
+                deps.storage,
+                start.as_ref().map(|((reliability, completed), address)| {
+                    Bound::exclusive((*reliability, *completed, address))
+                }),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .map(|key| {
+                let (_, _, address) = key?;
+                self.executor_stats_response(deps.storage, address)
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok(ExecutorsResponse { executors })
+    }
 }
 
 impl<Q: JsonSchema> From<QueryMsg<Q>> for Cw721QueryMsg<Q> {
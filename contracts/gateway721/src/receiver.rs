@@ -0,0 +1,83 @@
+use schemars::JsonSchema;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    from_json, to_json_binary, Addr, Binary, CosmosMsg, HexBinary, StdError, StdResult, WasmMsg,
+};
+use sha2::{Digest, Sha256};
+
+/// Version of the `Gateway721ReceiveMsg` layout emitted by this contract.
+pub const RECEIVE_MSG_VERSION: u32 = 1;
+
+/// Gateway721ReceiveMsg should be de/serialized under `ReceiveOutput()` variant in a ExecuteMsg
+/// of the destination contract.
+#[cw_serde]
+pub struct Gateway721ReceiveMsg {
+    /// Layout version, see `RECEIVE_MSG_VERSION`
+    pub version: u32,
+    pub token_id: String,
+    pub task_id: String,
+    /// Address that requested the task
+    pub requester: String,
+    /// SHA-256 of the task input
+    pub input_hash: HexBinary,
+    pub output: String,
+}
+
+impl Gateway721ReceiveMsg {
+    /// serializes the message
+    pub fn into_binary(self) -> StdResult<Binary> {
+        let msg = ReceiverExecuteMsg::ReceiveOutput(self);
+        to_json_binary(&msg)
+    }
+
+    /// creates a cosmos_msg sending this struct to the named contract
+    pub fn into_cosmos_msg<T: Into<String>, C>(self, contract_addr: T) -> StdResult<CosmosMsg<C>>
+    where
+        C: Clone + std::fmt::Debug + PartialEq + JsonSchema,
+    {
+        let msg = self.into_binary()?;
+        let execute = WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg,
+            funds: vec![],
+        };
+        Ok(execute.into())
+    }
+
+    /// deserializes a message serialized by `into_binary`
+    pub fn from_binary(msg: &Binary) -> StdResult<Self> {
+        match from_json(msg)? {
+            ReceiverExecuteMsg::ReceiveOutput(msg) => Ok(msg),
+        }
+    }
+
+    /// Checks that the message was sent by one of the given gateway contracts
+    /// and uses a layout version this crate understands.
+    pub fn verify(&self, sender: &Addr, gateways: &[Addr]) -> StdResult<()> {
+        if !gateways.contains(sender) {
+            return Err(StdError::generic_err(format!(
+                "{sender} is not a known gateway."
+            )));
+        }
+        if self.version > RECEIVE_MSG_VERSION {
+            return Err(StdError::generic_err(format!(
+                "unsupported receive message version {}.",
+                self.version
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Hashes a task input the same way the gateway does for `input_hash`
+pub fn input_hash(input: &str) -> HexBinary {
+    HexBinary::from(Sha256::digest(input.as_bytes()).to_vec())
+}
+
+/// This is just a helper to properly serialize the above message.
+/// The actual receiver should include this variant in the larger ExecuteMsg enum
+#[cw_serde]
+enum ReceiverExecuteMsg {
+    ReceiveOutput(Gateway721ReceiveMsg),
+}
@@ -1,21 +1,477 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, CustomMsg};
+use cosmwasm_std::{
+    Addr, Binary, BlockInfo, Coin, CustomMsg, Decimal, Empty, HexBinary, Timestamp, Uint128,
+};
 
-use cw_storage_plus::Item;
-use schemars::JsonSchema;
+use cw20::Denom;
+use cw721::Expiration;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 
 use cw721_base::Cw721Contract;
 
 use crate::msg::IncompleteProjectsResponse;
 
 #[cw_serde]
+#[derive(Default)]
 pub struct Task {
     pub tid: String,
+    pub requester: Option<Addr>,
+    /// Block height of the request
+    pub requested_at: Option<u64>,
+    /// Executors cannot respond before it expires
+    pub not_before: Option<Expiration>,
     pub input: String,
     pub output: Option<String>,
     // pub done: Option<bool>,
+    /// Set when the requester withdrew the task before it was responded
+    #[serde(default)]
+    pub cancelled: bool,
+    /// Executors whose output lost the quorum vote
+    #[serde(default)]
+    pub flagged: Vec<Addr>,
+}
+
+/// Output submitted by one executor for a task still waiting on its quorum
+#[cw_serde]
+pub struct Submission {
+    pub executor: Addr,
+    pub output_hash: HexBinary,
+    pub output: String,
+}
+
+/// Amount a project charges per request in one accepted denomination
+#[cw_serde]
+pub struct Price {
+    pub denom: Denom,
+    pub amount: Uint128,
+}
+
+/// Payment held by the contract until the task is responded or cancelled
+#[cw_serde]
+pub struct Escrow {
+    pub payer: Addr,
+    pub denom: Denom,
+    pub amount: Uint128,
+}
+
+/// Claimable amount of one denomination
+#[cw_serde]
+pub struct Balance {
+    pub denom: Denom,
+    pub amount: Uint128,
+}
+
+/// How request fees are split once a task is responded, shares sum to one
+#[cw_serde]
+pub struct RevenueShare {
+    /// Accrued to the token, claimable by whoever owns it at withdrawal
+    pub owner: Decimal,
+    /// Accrued to the address that responded the task
+    pub executor: Decimal,
+    pub treasury: Decimal,
+    pub treasury_address: Addr,
+}
+
+/// Bond executors must keep to respond and how misbehaviour is penalized
+#[cw_serde]
+pub struct StakingConfig {
+    /// Native denomination executors bond
+    pub denom: String,
+    /// Bond an executor needs to respond
+    pub min_bond: Uint128,
+    /// Seconds before unbonded stake can be claimed
+    pub unbonding_period: u64,
+    /// Portion of the stake taken for each offence
+    pub slash_ratio: Decimal,
+    /// Blocks an executor has to respond a claimed task or to reveal a committed output
+    pub response_timeout: u64,
+}
+
+/// Stake leaving the bond once `release_at` is reached
+#[cw_serde]
+pub struct Unbonding {
+    pub amount: Uint128,
+    pub release_at: Timestamp,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct Stake {
+    pub bonded: Uint128,
+    pub unbonding: Vec<Unbonding>,
+    /// Total taken from this executor so far
+    pub slashed: Uint128,
+}
+
+/// Exclusive right of an executor to respond a task until `expires_at`
+#[cw_serde]
+pub struct TaskClaim {
+    pub executor: Addr,
+    pub claimed_at: u64,
+    /// Last block height the executor can respond at
+    pub expires_at: u64,
+}
+
+/// Track record of an executor
+#[cw_serde]
+#[derive(Default)]
+pub struct ExecutorStats {
+    /// Tasks finalized with the executor's output
+    pub completed: u64,
+    /// Committed outputs not revealed in time
+    pub failed: u64,
+    /// Claimed tasks left unresponded past the claim
+    pub expired_claims: u64,
+    /// Blocks between request and finalization summed over completed tasks
+    pub total_latency_blocks: u64,
+    /// Outputs that lost a quorum vote
+    pub disagreements: u64,
+}
+
+impl ExecutorStats {
+    /// Share of completed tasks among all tasks the executor was accountable for
+    pub fn reliability(&self) -> Decimal {
+        let accountable = self.completed + self.failed + self.expired_claims + self.disagreements;
+        if accountable == 0 {
+            return Decimal::zero();
+        }
+        Decimal::from_ratio(self.completed, accountable)
+    }
+
+    /// Position in the executor ranking, ascending keys put higher reliability and then more
+    /// completed tasks first
+    pub fn rank(&self) -> (u128, u64) {
+        (
+            u128::MAX - self.reliability().atomics().u128(),
+            u64::MAX - self.completed,
+        )
+    }
+}
+
+#[cw_serde]
+pub enum KeyScheme {
+    Secp256k1,
+    Ed25519,
+}
+
+/// Off-chain worker key an executor signs attestations with
+#[cw_serde]
+pub struct ExecutorKey {
+    pub scheme: KeyScheme,
+    pub pubkey: Binary,
+}
+
+/// Time between runs of a schedule
+#[cw_serde]
+pub enum Interval {
+    Blocks(u64),
+    Seconds(u64),
+}
+
+impl Interval {
+    /// When the next run is due, counting from `block`
+    pub fn after(&self, block: &BlockInfo) -> Expiration {
+        match self {
+            Interval::Blocks(blocks) => Expiration::AtHeight(block.height + blocks),
+            Interval::Seconds(seconds) => Expiration::AtTime(block.time.plus_seconds(*seconds)),
+        }
+    }
+}
+
+/// Recurring request, prepaid for all of its runs
+#[cw_serde]
+pub struct Schedule {
+    pub owner: Addr,
+    pub token_id: String,
+    pub input: String,
+    pub interval: Interval,
+    pub max_runs: u32,
+    pub runs: u32,
+    pub next_run: Expiration,
+    pub paused: bool,
+    /// Paid for each run, held until the task is responded like any request
+    pub price: Option<Price>,
+}
+
+/// Schedules due at a height, sorted first in the due index
+pub const DUE_AT_HEIGHT: u8 = 0;
+/// Schedules due at a time
+pub const DUE_AT_TIME: u8 = 1;
+/// Paused schedules, never listed as due
+const NOT_DUE: u8 = 2;
+
+impl Schedule {
+    /// Kind of the next run and the height or nanoseconds it is due at
+    pub fn due_key(&self) -> (u8, u64) {
+        match self.next_run {
+            _ if self.paused => (NOT_DUE, 0),
+            Expiration::AtHeight(height) => (DUE_AT_HEIGHT, height),
+            Expiration::AtTime(time) => (DUE_AT_TIME, time.nanos()),
+            Expiration::Never {} => (NOT_DUE, 0),
+        }
+    }
+}
+
+pub struct ScheduleIndexes<'a> {
+    /// (kind, due), see `Schedule::due_key`
+    pub due: MultiIndex<'a, (u8, u64), Schedule, u64>,
+}
+
+impl<'a> IndexList<Schedule> for ScheduleIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Schedule>> + '_> {
+        let v: Vec<&dyn Index<Schedule>> = vec![&self.due];
+        Box::new(v.into_iter())
+    }
+}
+
+/// How a pipeline stage builds its input from the output of the previous stage
+#[cw_serde]
+pub enum InputMapping {
+    /// The previous output as is
+    Output,
+    /// The template with every `{{output}}` replaced by the previous output
+    Template(String),
+}
+
+impl InputMapping {
+    pub fn apply(&self, output: &str) -> String {
+        match self {
+            InputMapping::Output => output.to_string(),
+            InputMapping::Template(template) => template.replace("{{output}}", output),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct PipelineStage {
+    pub token_id: String,
+    /// Ignored on the first stage, which takes the input of the run
+    pub input: InputMapping,
+}
+
+/// Projects requested one after the other, each with the output of the previous one
+#[cw_serde]
+pub struct Pipeline {
+    pub owner: Addr,
+    pub stages: Vec<PipelineStage>,
+    /// Receives the output of the last stage
+    pub destination: Option<Addr>,
+}
+
+/// Where a request was paid from, so that a refund goes back there
+#[cw_serde]
+#[derive(Default)]
+pub enum PaymentSource {
+    /// Funds attached to the message, or nothing to pay
+    #[default]
+    Funds,
+    Credits,
+    /// The quota of the subscription period starting at `period_start`
+    Subscription {
+        period_start: Timestamp,
+    },
+}
+
+/// Pipeline in progress, holding the prices of the stages not requested yet
+#[cw_serde]
+pub struct PipelineRun {
+    pub pipeline_id: u64,
+    pub requester: Addr,
+    /// Index of the stage whose task is pending
+    pub stage: u32,
+    /// Paid upfront for every stage
+    pub payments: Vec<Option<Price>>,
+    /// Where each stage was paid from, attached funds for runs that predate it
+    #[serde(default)]
+    pub sources: Vec<PaymentSource>,
+    /// Why the current stage could not be requested, ending the run
+    #[serde(default)]
+    pub failure: Option<String>,
+}
+
+/// What created a task besides a plain request
+#[cw_serde]
+pub enum TaskLink {
+    Pipeline {
+        run_id: u64,
+    },
+    /// Child task whose output goes to position `index` of a composite task's output
+    Composite {
+        token_id: String,
+        task_id: String,
+        index: u32,
+    },
+}
+
+/// Token held by the contract until it is bought or delisted
+#[cw_serde]
+pub struct Listing {
+    pub seller: Addr,
+    pub price: Coin,
+}
+
+pub struct ListingIndexes<'a> {
+    pub seller: MultiIndex<'a, Addr, Listing, String>,
+    /// (denom, amount)
+    pub price: MultiIndex<'a, (String, u128), Listing, String>,
+}
+
+impl<'a> IndexList<Listing> for ListingIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Listing>> + '_> {
+        let v: Vec<&dyn Index<Listing>> = vec![&self.seller, &self.price];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Grant to request a private project
+#[cw_serde]
+pub struct License {
+    pub holder: Addr,
+    pub expires: Expiration,
+    /// Requests left, unlimited when unset
+    pub uses: Option<u32>,
+}
+
+impl License {
+    pub fn is_valid(&self, block: &BlockInfo) -> bool {
+        !self.expires.is_expired(block) && self.uses != Some(0)
+    }
+}
+
+pub struct LicenseIndexes<'a> {
+    pub holder: MultiIndex<'a, Addr, License, (String, Addr)>,
+}
+
+impl<'a> IndexList<License> for LicenseIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<License>> + '_> {
+        let v: Vec<&dyn Index<License>> = vec![&self.holder];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Terms anyone can buy a license to a project on
+#[cw_serde]
+pub struct LicenseOffer {
+    pub price: Coin,
+    /// How long a bought license lasts, forever when unset
+    pub duration: Option<Interval>,
+    pub uses: Option<u32>,
+}
+
+/// Access to a project for a fixed price per period
+#[cw_serde]
+pub struct Plan {
+    pub price: Coin,
+    /// Seconds
+    pub period: u64,
+    /// Requests per period, unlimited when unset
+    pub max_requests: Option<u32>,
+}
+
+/// Paid periods of a plan, with the terms it was subscribed on
+#[cw_serde]
+pub struct Subscription {
+    /// Index of the plan among the project plans when subscribed
+    pub plan_id: u32,
+    pub plan: Plan,
+    pub period_start: Timestamp,
+    pub expires: Timestamp,
+    /// Requests sent in the current period
+    pub requests: u32,
+}
+
+impl Subscription {
+    pub fn is_active(&self, block: &BlockInfo) -> bool {
+        block.time < self.expires
+    }
+
+    /// Starts a new period with a fresh quota once the current one is over
+    pub fn roll(&mut self, block: &BlockInfo) {
+        let elapsed = block.time.seconds() - self.period_start.seconds();
+        if elapsed >= self.plan.period {
+            let periods = elapsed / self.plan.period;
+            self.period_start = self.period_start.plus_seconds(periods * self.plan.period);
+            self.requests = 0;
+        }
+    }
+
+    pub fn remaining(&self) -> Option<u32> {
+        self.plan
+            .max_requests
+            .map(|max| max.saturating_sub(self.requests))
+    }
+}
+
+/// Privileges the contract owner can hand out, admins hold every role
+#[cw_serde]
+pub enum Role {
+    /// Grants and revokes roles, and does everything the other roles do
+    Admin,
+    /// Registers executors and configures staking
+    ExecutorManager,
+    /// Fills in project titles and descriptions
+    Describer,
+    /// Suspends any project
+    Moderator,
+    /// Configures fees and distributes rewards
+    Treasurer,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Role::Admin => "admin",
+            Role::ExecutorManager => "executor_manager",
+            Role::Describer => "describer",
+            Role::Moderator => "moderator",
+            Role::Treasurer => "treasurer",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Operations the contract owner can stop in an emergency
+#[cw_serde]
+#[derive(Default)]
+pub struct Pauses {
+    /// Mint and Fork
+    pub minting: bool,
+    /// Every message creating tasks
+    pub requests: bool,
+    /// Every message submitting or claiming outputs
+    pub responses: bool,
+    /// Every message moving tokens, the marketplace included
+    pub transfers: bool,
+}
+
+/// Who stopped a project, a moderator's suspension outlasts changes of owner
+#[cw_serde]
+pub struct Suspension {
+    pub suspended_by: Addr,
+    /// Set by a moderator rather than the token owner
+    pub moderated: bool,
+}
+
+#[cw_serde]
+pub struct RateLimit {
+    /// Tasks waiting on an output
+    pub max_pending: Option<u32>,
+    /// Requests within the last `window` blocks
+    pub max_requests: Option<u32>,
+    pub window: u64,
+}
+
+/// Request limits of a project, its owner is never limited
+#[cw_serde]
+pub struct RateLimits {
+    /// Applied to each requester on its own
+    pub per_address: Option<RateLimit>,
+    /// Applied to all requesters together
+    pub per_project: Option<RateLimit>,
+    /// Requesters neither limit applies to
+    pub exempt: Vec<Addr>,
 }
 
 #[cw_serde]
@@ -26,6 +482,18 @@ pub struct Metadata {
     pub destination: Option<Addr>,
     pub code: String,
     pub tasks: Option<Vec<Task>>,
+    /// Projects a composite fans its requests out to, in place of running `code`
+    #[serde(default)]
+    pub children: Option<Vec<String>>,
+    /// Project this one was forked from
+    #[serde(default)]
+    pub parent: Option<String>,
+    /// Percentage of secondary sales owed to `royalty_payment_address`, as in cw2981
+    #[serde(default)]
+    pub royalty_percentage: Option<u64>,
+    /// Defaults to the owner at mint when a royalty is set
+    #[serde(default)]
+    pub royalty_payment_address: Option<Addr>,
 }
 
 pub type Extension = Option<Metadata>;
@@ -39,6 +507,86 @@ where
     pub cw721: cw721_base::Cw721Contract<'a, T, C, E, Q>,
 
     pub incomplete_projects: Item<'a, IncompleteProjectsResponse>,
+    /// Accepted denominations and prices per token_id, free when unset
+    pub prices: Map<'a, &'a str, Vec<Price>>,
+    /// Payments held per (token_id, task_id)
+    pub escrows: Map<'a, (&'a str, &'a str), Escrow>,
+    /// Fee split, everything goes to the project owner when unset
+    pub revenue_share: Item<'a, RevenueShare>,
+    /// Claimable balances of executors and the treasury
+    pub balances: Map<'a, &'a Addr, Vec<Balance>>,
+    /// Claimable balances accrued to each token_id for its owner
+    pub token_balances: Map<'a, &'a str, Vec<Balance>>,
+    /// Tasks completed since the last Archway rewards distribution, per executor and project owner
+    pub reward_points: Map<'a, &'a Addr, u64>,
+    pub reward_points_total: Item<'a, u64>,
+    /// Registered executors allowed to respond besides the contract owner
+    pub executors: Map<'a, &'a Addr, Empty>,
+    /// Number of agreeing executors needed to finalize a task, per token_id
+    pub quorums: Map<'a, &'a str, u32>,
+    /// Outputs submitted per (token_id, task_id) until the quorum agrees
+    pub submissions: Map<'a, (&'a str, &'a str), Vec<Submission>>,
+    /// Blocks after a request during which executors commit to output hashes, per token_id
+    pub commit_windows: Map<'a, &'a str, u64>,
+    /// Output hashes committed per (token_id, task_id, executor)
+    pub commitments: Map<'a, (&'a str, &'a str, &'a Addr), HexBinary>,
+    /// Executor bonding, executors need no stake when unset
+    pub staking: Item<'a, StakingConfig>,
+    pub stakes: Map<'a, &'a Addr, Stake>,
+    /// Claimed tasks per (token_id, task_id)
+    pub claims: Map<'a, (&'a str, &'a str), TaskClaim>,
+    /// Attestation keys per executor
+    pub executor_keys: Map<'a, &'a Addr, ExecutorKey>,
+    pub executor_stats: Map<'a, &'a Addr, ExecutorStats>,
+    /// Registered executors keyed by the rank of their stats
+    pub executor_ranks: Map<'a, (u128, u64, &'a Addr), Empty>,
+    /// Executors allowed to work on each token_id, any registered executor when unset
+    pub allowlists: Map<'a, &'a str, Vec<Addr>>,
+    pub schedules: IndexedMap<'a, u64, Schedule, ScheduleIndexes<'a>>,
+    pub schedule_count: Item<'a, u64>,
+    pub pipelines: Map<'a, u64, Pipeline>,
+    pub pipeline_count: Item<'a, u64>,
+    pub pipeline_runs: Map<'a, u64, PipelineRun>,
+    pub pipeline_run_count: Item<'a, u64>,
+    /// Pipeline runs and composite tasks waiting on each (token_id, task_id)
+    pub task_links: Map<'a, (&'a str, &'a str), TaskLink>,
+    /// Child outputs collected per composite (token_id, task_id)
+    pub fan_outs: Map<'a, (&'a str, &'a str), Vec<Option<String>>>,
+    /// Executors of the finalized children per composite (token_id, task_id)
+    pub fan_out_executors: Map<'a, (&'a str, &'a str), Vec<Addr>>,
+    /// Forks per (parent token_id, fork token_id)
+    pub forks: Map<'a, (&'a str, &'a str), Empty>,
+    /// Portion of a fork's fee share passed on to its parent, nothing when unset
+    pub lineage_share: Item<'a, Decimal>,
+    /// Highest royalty percentage a project can be minted with, up to 100 when unset
+    pub max_royalty_percentage: Item<'a, u64>,
+    pub listings: IndexedMap<'a, &'a str, Listing, ListingIndexes<'a>>,
+    /// Portion of each marketplace sale taken for the treasury, nothing when unset
+    pub market_fee: Item<'a, Decimal>,
+    /// Projects only their owner and license holders can request
+    pub private_projects: Map<'a, &'a str, Empty>,
+    /// Licenses per (token_id, holder)
+    pub licenses: IndexedMap<'a, (&'a str, &'a Addr), License, LicenseIndexes<'a>>,
+    pub license_offers: Map<'a, &'a str, LicenseOffer>,
+    /// Prepaid native funds requests without attached funds are paid from
+    pub credits: Map<'a, &'a Addr, Vec<Balance>>,
+    /// Subscription plans per token_id
+    pub plans: Map<'a, &'a str, Vec<Plan>>,
+    /// Subscriptions per (token_id, subscriber)
+    pub subscriptions: Map<'a, (&'a str, &'a Addr), Subscription>,
+    /// Request limits per token_id, unlimited when unset
+    pub rate_limits: Map<'a, &'a str, RateLimits>,
+    pub pauses: Item<'a, Pauses>,
+    /// Projects that can neither be requested nor responded to, with who suspended them
+    pub suspended: Map<'a, &'a str, Suspension>,
+    /// Heights responses were paused at, to the height they resumed at or `u64::MAX` while
+    /// paused, so that slashing deadlines leave the paused blocks out
+    pub response_pauses: Map<'a, u64, u64>,
+    /// Heights each project was suspended at, to the height it was lifted at or `u64::MAX`
+    /// while suspended
+    pub suspensions: Map<'a, (&'a str, u64), u64>,
+    /// Roles granted per address
+    pub roles: Map<'a, &'a Addr, Vec<Role>>,
 }
 
 impl<T, C, E, Q> Default for Gateway721Contract<'static, T, C, E, Q>
@@ -61,14 +609,110 @@ where
     fn new(incomplete_projects_key: &'a str) -> Self {
         Self {
             incomplete_projects: Item::new(incomplete_projects_key),
+            prices: Map::new("prices"),
+            escrows: Map::new("escrows"),
+            revenue_share: Item::new("revenue_share"),
+            balances: Map::new("balances"),
+            token_balances: Map::new("token_balances"),
+            reward_points: Map::new("reward_points"),
+            reward_points_total: Item::new("reward_points_total"),
+            executors: Map::new("executors"),
+            quorums: Map::new("quorums"),
+            submissions: Map::new("submissions"),
+            commit_windows: Map::new("commit_windows"),
+            commitments: Map::new("commitments"),
+            staking: Item::new("staking"),
+            stakes: Map::new("stakes"),
+            claims: Map::new("claims"),
+            executor_keys: Map::new("executor_keys"),
+            executor_stats: Map::new("executor_stats"),
+            executor_ranks: Map::new("executor_ranks"),
+            allowlists: Map::new("allowlists"),
+            schedules: IndexedMap::new(
+                "schedules",
+                ScheduleIndexes {
+                    due: MultiIndex::new(
+                        |_, schedule| schedule.due_key(),
+                        "schedules",
+                        "schedules__due",
+                    ),
+                },
+            ),
+            schedule_count: Item::new("schedule_count"),
+            pipelines: Map::new("pipelines"),
+            pipeline_count: Item::new("pipeline_count"),
+            pipeline_runs: Map::new("pipeline_runs"),
+            pipeline_run_count: Item::new("pipeline_run_count"),
+            task_links: Map::new("task_links"),
+            fan_outs: Map::new("fan_outs"),
+            fan_out_executors: Map::new("fan_out_executors"),
+            forks: Map::new("forks"),
+            lineage_share: Item::new("lineage_share"),
+            max_royalty_percentage: Item::new("max_royalty_percentage"),
+            listings: IndexedMap::new(
+                "listings",
+                ListingIndexes {
+                    seller: MultiIndex::new(
+                        |_, listing| listing.seller.clone(),
+                        "listings",
+                        "listings__seller",
+                    ),
+                    price: MultiIndex::new(
+                        |_, listing| (listing.price.denom.clone(), listing.price.amount.u128()),
+                        "listings",
+                        "listings__price",
+                    ),
+                },
+            ),
+            market_fee: Item::new("market_fee"),
+            private_projects: Map::new("private_projects"),
+            licenses: IndexedMap::new(
+                "licenses",
+                LicenseIndexes {
+                    holder: MultiIndex::new(
+                        |_, license| license.holder.clone(),
+                        "licenses",
+                        "licenses__holder",
+                    ),
+                },
+            ),
+            license_offers: Map::new("license_offers"),
+            credits: Map::new("credits"),
+            plans: Map::new("plans"),
+            subscriptions: Map::new("subscriptions"),
+            rate_limits: Map::new("rate_limits"),
+            pauses: Item::new("pauses"),
+            suspended: Map::new("suspended"),
+            response_pauses: Map::new("response_pauses"),
+            suspensions: Map::new("suspensions"),
+            roles: Map::new("roles"),
             cw721: Cw721Contract::default(),
         }
     }
 }
 
-// Callee
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case")]
-pub enum OtherContractExecuteMsg {
-    ReceiveOutput { output: String },
+/// Takes `amount` of `denom` out of a list of balances, leaving them untouched if they fall short
+pub fn take_balance(balances: &mut Vec<Balance>, denom: &Denom, amount: Uint128) -> Option<()> {
+    let index = balances
+        .iter()
+        .position(|balance| &balance.denom == denom && balance.amount >= amount)?;
+    balances[index].amount -= amount;
+    if balances[index].amount.is_zero() {
+        balances.remove(index);
+    }
+    Some(())
+}
+
+/// Adds `amount` of `denom` to a list of balances
+pub fn add_balance(balances: &mut Vec<Balance>, denom: &Denom, amount: Uint128) {
+    if amount.is_zero() {
+        return;
+    }
+    match balances.iter_mut().find(|balance| &balance.denom == denom) {
+        Some(balance) => balance.amount += amount,
+        None => balances.push(Balance {
+            denom: denom.clone(),
+            amount,
+        }),
+    }
 }
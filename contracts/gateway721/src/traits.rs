@@ -1,9 +1,25 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use cosmwasm_std::{CustomMsg, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{
+    Binary, Coin, CustomMsg, Decimal, Deps, DepsMut, Env, HexBinary, MessageInfo, Response,
+    StdResult, Uint128,
+};
+use cw20::Cw20ReceiveMsg;
+use cw721::Expiration;
 
-use crate::msg::{IncompleteProjectsResponse, TaskIdsResponse};
+use crate::msg::{
+    AllowlistResponse, Attestation, BalancesResponse, CheckRoyaltiesResponse, CommitmentsResponse,
+    ExecutorStatsResponse, ExecutorsResponse, IncompleteProjectsResponse, LicensesResponse,
+    LineageResponse, ListingCursor, ListingsResponse, PipelineResponse, PlansResponse,
+    PricesResponse, QuorumResponse, RequestItem, ResponseItem, RoyaltiesInfoResponse,
+    ScheduleResponse, SchedulesResponse, StakeResponse, StatusResponse, SubmissionsResponse,
+    SubscriptionResponse, TaskIdsResponse,
+};
+use crate::state::{
+    ExecutorKey, Interval, KeyScheme, LicenseOffer, Listing, PipelineRun, PipelineStage, Plan,
+    Price, RateLimit, RateLimits, RevenueShare, Role, StakingConfig, TaskClaim,
+};
 
 pub trait Gateway721<T, C>: Gateway721Execute<T, C> + Gateway721Query<T>
 where
@@ -26,8 +42,10 @@ where
         info: MessageInfo,
         token_id: String,
         input: String,
+        not_before: Option<Expiration>,
     ) -> Result<Response<C>, Self::Err>;
 
+    #[allow(clippy::too_many_arguments)]
     fn response(
         &self,
         deps: DepsMut,
@@ -36,6 +54,7 @@ where
         token_id: String,
         task_id: String,
         output: String,
+        attestation: Option<Attestation>,
     ) -> Result<Response<C>, Self::Err>;
 
     fn update(
@@ -47,13 +66,605 @@ where
         title: String,
         description: String,
     ) -> Result<Response<C>, Self::Err>;
+
+    fn receive(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Cw20ReceiveMsg,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn cancel_request(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        task_id: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn set_prices(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        prices: Vec<Price>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn set_revenue_share(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        owner: Decimal,
+        executor: Decimal,
+        treasury: Decimal,
+        treasury_address: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn deposit_credits(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn withdraw_credits(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        amount: Option<Coin>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn set_plans(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        plans: Vec<Plan>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn subscribe(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        plan: u32,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn unsubscribe(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn set_pauses(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        minting: Option<bool>,
+        requests: Option<bool>,
+        responses: Option<bool>,
+        transfers: Option<bool>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn suspend(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        suspended: bool,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn set_role(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        address: String,
+        role: Role,
+        granted: bool,
+    ) -> Result<Response<C>, Self::Err>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn set_rate_limits(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        per_address: Option<RateLimit>,
+        per_project: Option<RateLimit>,
+        exempt: Vec<String>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn set_private(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        private: bool,
+    ) -> Result<Response<C>, Self::Err>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn issue_license(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        holder: String,
+        expires: Option<Expiration>,
+        uses: Option<u32>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn revoke_license(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        holder: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn set_license_offer(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        offer: Option<LicenseOffer>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn buy_license(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn list_nft(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        price: Coin,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn delist_nft(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn buy_nft(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn set_market_fee(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        fee: Decimal,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn set_max_royalty_percentage(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        percentage: u64,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn set_lineage_share(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        share: Decimal,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn fork(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        code: Option<String>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn withdraw(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn add_executor(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        address: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn remove_executor(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        address: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn set_quorum(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        quorum: u32,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn set_commit_window(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        blocks: u64,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn commit_output(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        task_id: String,
+        hash: HexBinary,
+    ) -> Result<Response<C>, Self::Err>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn reveal_output(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        task_id: String,
+        output: String,
+        salt: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn set_staking(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        denom: String,
+        min_bond: Uint128,
+        unbonding_period: u64,
+        slash_ratio: Decimal,
+        response_timeout: u64,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn register_executor(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn unbond(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        amount: Uint128,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn claim_unbonded(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn claim_task(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        task_id: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn slash_expired_claim(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        task_id: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn slash_unrevealed(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        task_id: String,
+        executor: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn set_allowlist(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        executors: Vec<String>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn schedule_request(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+        input: String,
+        interval: Interval,
+        max_runs: u32,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn trigger_schedule(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        id: u64,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn pause_schedule(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        id: u64,
+        paused: bool,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn cancel_schedule(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        id: u64,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn batch_request(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        items: Vec<RequestItem>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn batch_response(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        items: Vec<ResponseItem>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn create_pipeline(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        stages: Vec<PipelineStage>,
+        destination: Option<String>,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn run_pipeline(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        id: u64,
+        input: String,
+    ) -> Result<Response<C>, Self::Err>;
+
+    fn set_executor_key(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        scheme: KeyScheme,
+        pubkey: Binary,
+    ) -> Result<Response<C>, Self::Err>;
 }
 
 pub trait Gateway721Query<T>
 where
     T: Serialize + DeserializeOwned + Clone,
 {
-    fn remains(&self, deps: Deps, token_id: String) -> StdResult<TaskIdsResponse>;
+    fn remains(&self, deps: Deps, env: Env, token_id: String) -> StdResult<TaskIdsResponse>;
 
     fn incomplete_projects(&self, deps: Deps) -> StdResult<IncompleteProjectsResponse>;
+
+    fn prices(&self, deps: Deps, token_id: String) -> StdResult<PricesResponse>;
+
+    fn revenue_share(&self, deps: Deps) -> StdResult<Option<RevenueShare>>;
+
+    fn royalty_info(
+        &self,
+        deps: Deps,
+        token_id: String,
+        sale_price: Uint128,
+    ) -> StdResult<RoyaltiesInfoResponse>;
+
+    fn check_royalties(&self, deps: Deps) -> StdResult<CheckRoyaltiesResponse>;
+
+    fn credits(&self, deps: Deps, address: String) -> StdResult<BalancesResponse>;
+
+    fn plans(&self, deps: Deps, token_id: String) -> StdResult<PlansResponse>;
+
+    fn subscription(
+        &self,
+        deps: Deps,
+        env: Env,
+        token_id: String,
+        subscriber: String,
+    ) -> StdResult<SubscriptionResponse>;
+
+    fn status(&self, deps: Deps, token_id: Option<String>) -> StdResult<StatusResponse>;
+
+    fn roles(&self, deps: Deps, address: String) -> StdResult<Vec<Role>>;
+
+    fn rate_limits(&self, deps: Deps, token_id: String) -> StdResult<Option<RateLimits>>;
+
+    fn private(&self, deps: Deps, token_id: String) -> StdResult<bool>;
+
+    fn licenses(
+        &self,
+        deps: Deps,
+        token_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<LicensesResponse>;
+
+    fn holder_licenses(
+        &self,
+        deps: Deps,
+        holder: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<LicensesResponse>;
+
+    fn license_offer(&self, deps: Deps, token_id: String) -> StdResult<Option<LicenseOffer>>;
+
+    fn listing(&self, deps: Deps, token_id: String) -> StdResult<Option<Listing>>;
+
+    fn listings(
+        &self,
+        deps: Deps,
+        seller: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<ListingsResponse>;
+
+    fn listings_by_price(
+        &self,
+        deps: Deps,
+        denom: String,
+        start_after: Option<ListingCursor>,
+        limit: Option<u32>,
+    ) -> StdResult<ListingsResponse>;
+
+    fn market_fee(&self, deps: Deps) -> StdResult<Option<Decimal>>;
+
+    fn max_royalty_percentage(&self, deps: Deps) -> StdResult<u64>;
+
+    fn lineage_share(&self, deps: Deps) -> StdResult<Option<Decimal>>;
+
+    fn ancestors(
+        &self,
+        deps: Deps,
+        token_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<LineageResponse>;
+
+    fn descendants(
+        &self,
+        deps: Deps,
+        token_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<LineageResponse>;
+
+    fn balances(
+        &self,
+        deps: Deps,
+        address: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<BalancesResponse>;
+
+    fn quorum(&self, deps: Deps, token_id: String) -> StdResult<QuorumResponse>;
+
+    fn allowlist(&self, deps: Deps, token_id: String) -> StdResult<AllowlistResponse>;
+
+    fn schedule(&self, deps: Deps, id: u64) -> StdResult<ScheduleResponse>;
+
+    fn pipeline(&self, deps: Deps, id: u64) -> StdResult<PipelineResponse>;
+
+    fn pipeline_run(&self, deps: Deps, id: u64) -> StdResult<Option<PipelineRun>>;
+
+    fn due_schedules(
+        &self,
+        deps: Deps,
+        env: Env,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<SchedulesResponse>;
+
+    fn submissions(
+        &self,
+        deps: Deps,
+        token_id: String,
+        task_id: String,
+    ) -> StdResult<SubmissionsResponse>;
+
+    fn commitments(
+        &self,
+        deps: Deps,
+        token_id: String,
+        task_id: String,
+    ) -> StdResult<CommitmentsResponse>;
+
+    fn staking(&self, deps: Deps) -> StdResult<Option<StakingConfig>>;
+
+    fn stake(&self, deps: Deps, address: String) -> StdResult<StakeResponse>;
+
+    fn claim(&self, deps: Deps, token_id: String, task_id: String) -> StdResult<Option<TaskClaim>>;
+
+    fn executor_key(&self, deps: Deps, address: String) -> StdResult<Option<ExecutorKey>>;
+
+    fn executor_stats(&self, deps: Deps, address: String) -> StdResult<ExecutorStatsResponse>;
+
+    fn executors(
+        &self,
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<ExecutorsResponse>;
 }